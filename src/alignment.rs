@@ -0,0 +1,92 @@
+use super::matrix3::Matrix3;
+use super::matrix4::Matrix4;
+use super::quaternion::Quaternion;
+use super::scalar::Float;
+use super::vector::Vector3;
+
+// similarity transform with target ~ scale * rotation * source + translation
+#[derive(Debug, Copy, Clone)]
+pub struct Alignment<T> {
+        pub rotation: Quaternion<T>,
+        pub matrix: Matrix3<T>,
+        pub translation: Vector3<T>,
+        // 1 unless the scale was estimated
+        pub scale: T,
+        // root mean square distance between the aligned source and the target
+        pub rmsd: T,
+        // the unconstrained least-squares fit is a mirror image. rotation is still the
+        // best proper rotation (Kabsch's determinant correction), so rmsd is larger than
+        // a reflection would give.
+        pub reflection: bool,
+}
+
+impl<T: Float> Alignment<T> {
+        pub fn transform_point(&self, p: Vector3<T>) -> Vector3<T> {
+                (self.matrix * p).scalar_mul(self.scale) + self.translation
+        }
+}
+
+fn centroid<T: Float>(points: &[Vector3<T>]) -> Vector3<T> {
+        let sum: Vector3<T> = points.iter().sum();
+        sum.scalar_div(T::from_f64(points.len() as f64))
+}
+
+// least-squares alignment of corresponding points, source[i] is moved onto target[i].
+// the rotation comes from Horn's quaternion method, the scale (when estimate_scale is set)
+// from Umeyama. returns None for empty or differently sized inputs.
+pub fn align<T: Float>(source: &[Vector3<T>], target: &[Vector3<T>], estimate_scale: bool) -> Option<Alignment<T>> {
+        if source.is_empty() || source.len() != target.len() {
+                return None;
+        }
+        let cs = centroid(source);
+        let ct = centroid(target);
+
+        // cross-covariance s[a][b] = sum(x_a * y_b) of the centered points
+        let mut s = [[T::zero(); 3]; 3];
+        let mut source_var = T::zero();
+        for (x, y) in source.iter().zip(target.iter()) {
+                let x = *x - cs;
+                let y = *y - ct;
+                for (a, row) in s.iter_mut().enumerate() {
+                        for (b, e) in row.iter_mut().enumerate() {
+                                *e += x[a] * y[b];
+                        }
+                }
+                source_var += x.dot(&x);
+        }
+
+        let n = Matrix4 { v: [
+                s[0][0] + s[1][1] + s[2][2], s[1][2] - s[2][1], s[2][0] - s[0][2], s[0][1] - s[1][0],
+                s[1][2] - s[2][1], s[0][0] - s[1][1] - s[2][2], s[0][1] + s[1][0], s[2][0] + s[0][2],
+                s[2][0] - s[0][2], s[0][1] + s[1][0], -s[0][0] + s[1][1] - s[2][2], s[1][2] + s[2][1],
+                s[0][1] - s[1][0], s[2][0] + s[0][2], s[1][2] + s[2][1], -s[0][0] - s[1][1] + s[2][2] ] };
+        // the eigenvector of the largest eigenvalue is the optimal rotation
+        let (_, vectors) = n.symmetric_eigen();
+        let rotation = Quaternion::new(vectors.m00(), vectors.m10(), vectors.m20(), vectors.m30()).unitize();
+        let rotation = if rotation.w() < T::zero() { -rotation } else { rotation };
+        let matrix = Matrix3::from(rotation);
+
+        let scale = if estimate_scale && source_var > T::zero() {
+                source.iter().zip(target.iter())
+                        .fold(T::zero(), |sum, (x, y)| sum + (*y - ct).dot(&(matrix * (*x - cs))))
+                        / source_var
+        } else {
+                T::one()
+        };
+        let translation = ct - (matrix * cs).scalar_mul(scale);
+
+        let covariance = Matrix3 { v: [
+                s[0][0], s[0][1], s[0][2],
+                s[1][0], s[1][1], s[1][2],
+                s[2][0], s[2][1], s[2][2] ] };
+        let size = covariance.v.iter().fold(T::zero(), |m, e| m + *e * *e).sqrt();
+        let reflection = covariance.determinant() < -T::epsilon().sqrt() * size * size * size;
+
+        let mut alignment = Alignment { rotation, matrix, translation, scale, rmsd: T::zero(), reflection };
+        let squared = source.iter().zip(target.iter()).fold(T::zero(), |sum, (x, y)| {
+                let d = alignment.transform_point(*x) - *y;
+                sum + d.dot(&d)
+        });
+        alignment.rmsd = (squared / T::from_f64(source.len() as f64)).sqrt();
+        Some(alignment)
+}
@@ -0,0 +1,96 @@
+use super::matrix3::Matrix3;
+use super::matrix4::Matrix4;
+use super::quaternion::Quaternion;
+use super::scalar::Float;
+use super::vector::Vector3;
+
+fn weight_sum<T: Float>(qs: &[Quaternion<T>], weights: &[T]) -> T {
+        qs.iter().zip(weights.iter()).fold(T::zero(), |s, (_, w)| s + *w)
+}
+
+impl<T: Float> Quaternion<T> {
+        // weighted average after Markley et al. (2007): the eigenvector of the largest
+        // eigenvalue of sum(w * q * q^T). q and -q contribute the same, so inputs from
+        // opposite hemispheres are handled. The result is flipped into the hemisphere of
+        // the first input. returns None when there is nothing to average or the weights sum to zero.
+        pub fn average(qs: &[Quaternion<T>], weights: &[T]) -> Option<Quaternion<T>> {
+                assert_eq!(qs.len(), weights.len(), "one weight per quaternion");
+                let first = *qs.first()?;
+                if weight_sum(qs, weights) == T::zero() {
+                        return None;
+                }
+                let mut m = Matrix4::zero();
+                for (q, w) in qs.iter().zip(weights.iter()) {
+                        let q = q.unitize();
+                        let e = [q.w(), q.x(), q.y(), q.z()];
+                        for (i, entry) in m.v.iter_mut().enumerate() {
+                                *entry += *w * e[i / 4] * e[i % 4];
+                        }
+                }
+                let (_, vectors) = m.symmetric_eigen();
+                let mean = Quaternion::new(vectors.m00(), vectors.m10(), vectors.m20(), vectors.m30()).unitize();
+                if mean.dot(&first) < T::zero() { Some(-mean) } else { Some(mean) }
+        }
+
+        // rotation minimizing the weighted sum of squared frobenius distances between
+        // rotation matrices: the weighted matrix mean projected back onto SO(3).
+        // It is the same rotation Markley's method finds, only reached through matrices.
+        // The mean can have a negative determinant (rx(pi) + ry(pi) + rz(pi) = -I), so the
+        // projection is u * diag(1, 1, det(u v^T)) * v^T rather than the plain polar factor.
+        pub fn chordal_mean(qs: &[Quaternion<T>], weights: &[T]) -> Option<Quaternion<T>> {
+                assert_eq!(qs.len(), weights.len(), "one weight per quaternion");
+                let first = *qs.first()?;
+                let total = weight_sum(qs, weights);
+                if total == T::zero() {
+                        return None;
+                }
+                let m = qs.iter().zip(weights.iter())
+                        .fold(Matrix3::zero(), |m, (q, w)| m + Matrix3::from(q.unitize()) * *w);
+                let (u, _, v) = (m * (T::one() / total)).svd();
+                let mut d = Matrix3::identity();
+                if (u * v.transpose()).determinant() < T::zero() {
+                        d.v[8] = -T::one();
+                }
+                let mean = Quaternion::from(u * d * v.transpose());
+                if mean.dot(&first) < T::zero() { Some(-mean) } else { Some(mean) }
+        }
+
+        // karcher mean, minimizing the weighted sum of squared rotation angles to the inputs.
+        // starts from the markley average and steps along the mean tangent vector.
+        pub fn geodesic_mean(qs: &[Quaternion<T>], weights: &[T]) -> Option<Quaternion<T>> {
+                assert_eq!(qs.len(), weights.len(), "one weight per quaternion");
+                let total = weight_sum(qs, weights);
+                let mut mean = Quaternion::average(qs, weights)?;
+                for _ in 0..32 {
+                        let step = qs.iter().zip(weights.iter())
+                                .fold(Vector3::zero(), |s, (q, w)| {
+                                        s + (mean.conjugate() * q.unitize()).to_rotation_vector().scalar_mul(*w)
+                                })
+                                .scalar_div(total);
+                        mean = (mean * Quaternion::new_from_rotation_vector(step)).unitize();
+                        if step.length() < T::epsilon().sqrt() {
+                                break;
+                        }
+                }
+                Some(mean)
+        }
+
+        // angle in [0, pi] of the rotation taking self to other, q and -q are the same rotation
+        pub fn angular_distance(&self, other: &Quaternion<T>) -> T {
+                let d = self.unitize().conjugate() * other.unitize();
+                let s = Vector3::from(d).length();
+                s.atan2(d.w().abs()) * T::from_f64(2.0)
+        }
+
+        // weighted root mean square angular distance of qs from self (usually one of the means)
+        pub fn dispersion(&self, qs: &[Quaternion<T>], weights: &[T]) -> T {
+                assert_eq!(qs.len(), weights.len(), "one weight per quaternion");
+                let total = weight_sum(qs, weights);
+                if total == T::zero() {
+                        return T::zero();
+                }
+                let sum = qs.iter().zip(weights.iter())
+                        .fold(T::zero(), |s, (q, w)| s + *w * self.angular_distance(q).powi(2));
+                (sum / total).sqrt()
+        }
+}
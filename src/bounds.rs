@@ -0,0 +1,714 @@
+use super::matrix3::Matrix3;
+use super::primitive::{Ray, RayHit, Segment};
+use super::quaternion::Quaternion;
+use super::sampling::Rng;
+use super::scalar::Float;
+use super::vector::Vector3;
+
+// axis aligned box. The empty box has min = +inf and max = -inf so that merging
+// and growing need no special case.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Aabb<T> {
+        pub min: Vector3<T>,
+        pub max: Vector3<T>,
+}
+
+// oriented box, rotation holds the box axes as columns and half_extents the
+// half size along each of them
+#[derive(Debug, Copy, Clone)]
+pub struct Obb<T> {
+        pub center: Vector3<T>,
+        pub half_extents: Vector3<T>,
+        pub rotation: Matrix3<T>,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Sphere<T> {
+        pub center: Vector3<T>,
+        pub radius: T,
+}
+
+// all points within radius of the segment
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Capsule<T> {
+        pub segment: Segment<T>,
+        pub radius: T,
+}
+
+fn infinity<T: Float>() -> T {
+        T::from_f64(f64::INFINITY)
+}
+
+fn component_min<T: Float>(a: Vector3<T>, b: Vector3<T>) -> Vector3<T> {
+        Vector3::new(a.x().min(b.x()), a.y().min(b.y()), a.z().min(b.z()))
+}
+
+fn component_max<T: Float>(a: Vector3<T>, b: Vector3<T>) -> Vector3<T> {
+        Vector3::new(a.x().max(b.x()), a.y().max(b.y()), a.z().max(b.z()))
+}
+
+fn abs_matrix<T: Float>(m: Matrix3<T>) -> Matrix3<T> {
+        let mut r = m;
+        for e in r.v.iter_mut() {
+                *e = e.abs();
+        }
+        r
+}
+
+fn centroid<T: Float>(points: &[Vector3<T>]) -> Vector3<T> {
+        let sum: Vector3<T> = points.iter().sum();
+        sum.scalar_div(T::from_f64(points.len() as f64))
+}
+
+// principal axes of a point set as the columns of a proper rotation, largest spread first
+fn principal_axes<T: Float>(points: &[Vector3<T>]) -> Matrix3<T> {
+        let mean = centroid(points);
+        let mut covariance = Matrix3::zero();
+        for p in points.iter() {
+                let d = *p - mean;
+                for (i, e) in covariance.v.iter_mut().enumerate() {
+                        *e += d[i / 3] * d[i % 3];
+                }
+        }
+        let (_, axes) = covariance.symmetric_eigen();
+        if axes.determinant() < T::zero() {
+                Matrix3::new_from_cols(axes.col(0), axes.col(1), -axes.col(2))
+        } else {
+                axes
+        }
+}
+
+// both parameters where origin + t * direction meets the sphere surface, t0 <= t1
+fn ray_sphere<T: Float>(ray: &Ray<T>, center: Vector3<T>, radius: T) -> Option<(T, T)> {
+        let m = ray.origin - center;
+        let a = ray.direction.dot(&ray.direction);
+        let b = m.dot(&ray.direction);
+        let c = m.dot(&m) - radius * radius;
+        let disc = b * b - a * c;
+        if disc < T::zero() || a == T::zero() {
+                return None;
+        }
+        let root = disc.sqrt();
+        Some(((-b - root) / a, (-b + root) / a))
+}
+
+// first of t0, t1 that is not behind the origin
+fn first_ahead<T: Float>(t0: T, t1: T) -> Option<T> {
+        if t0 >= T::zero() {
+                Some(t0)
+        } else if t1 >= T::zero() {
+                Some(t1)
+        } else {
+                None
+        }
+}
+
+impl<T: Float> Aabb<T> {
+        pub fn new(min: Vector3<T>, max: Vector3<T>) -> Aabb<T> {
+                Aabb { min, max }
+        }
+
+        pub fn empty() -> Aabb<T> {
+                let inf = infinity();
+                Aabb { min: Vector3::new(inf, inf, inf), max: Vector3::new(-inf, -inf, -inf) }
+        }
+
+        // None for an empty slice
+        pub fn from_points(points: &[Vector3<T>]) -> Option<Aabb<T>> {
+                if points.is_empty() {
+                        return None;
+                }
+                Some(points.iter().fold(Aabb::empty(), |b, p| b.grow(*p)))
+        }
+
+        pub fn is_empty(&self) -> bool {
+                self.min.x() > self.max.x() || self.min.y() > self.max.y() || self.min.z() > self.max.z()
+        }
+
+        pub fn center(&self) -> Vector3<T> {
+                (self.min + self.max).scalar_mul(T::from_f64(0.5))
+        }
+
+        pub fn size(&self) -> Vector3<T> {
+                self.max - self.min
+        }
+
+        pub fn half_extents(&self) -> Vector3<T> {
+                self.size().scalar_mul(T::from_f64(0.5))
+        }
+
+        // zero for an empty box
+        pub fn surface_area(&self) -> T {
+                if self.is_empty() {
+                        return T::zero();
+                }
+                let s = self.size();
+                T::from_f64(2.0) * (s.x() * s.y() + s.y() * s.z() + s.z() * s.x())
+        }
+
+        pub fn volume(&self) -> T {
+                if self.is_empty() {
+                        return T::zero();
+                }
+                let s = self.size();
+                s.x() * s.y() * s.z()
+        }
+
+        pub fn grow(&self, p: Vector3<T>) -> Aabb<T> {
+                Aabb { min: component_min(self.min, p), max: component_max(self.max, p) }
+        }
+
+        pub fn merge(&self, other: &Aabb<T>) -> Aabb<T> {
+                Aabb { min: component_min(self.min, other.min), max: component_max(self.max, other.max) }
+        }
+
+        pub fn corners(&self) -> [Vector3<T>; 8] {
+                let (a, b) = (self.min, self.max);
+                [
+                        Vector3::new(a.x(), a.y(), a.z()), Vector3::new(b.x(), a.y(), a.z()),
+                        Vector3::new(a.x(), b.y(), a.z()), Vector3::new(b.x(), b.y(), a.z()),
+                        Vector3::new(a.x(), a.y(), b.z()), Vector3::new(b.x(), a.y(), b.z()),
+                        Vector3::new(a.x(), b.y(), b.z()), Vector3::new(b.x(), b.y(), b.z()),
+                ]
+        }
+
+        // the box around the rotated and translated box (Arvo)
+        pub fn transform(&self, rotation: Matrix3<T>, translation: Vector3<T>) -> Aabb<T> {
+                let center = rotation * self.center() + translation;
+                let half = abs_matrix(rotation) * self.half_extents();
+                Aabb { min: center - half, max: center + half }
+        }
+
+        pub fn closest_point(&self, p: Vector3<T>) -> Vector3<T> {
+                component_min(component_max(p, self.min), self.max)
+        }
+
+        pub fn contains_point(&self, p: Vector3<T>) -> bool {
+                (0..3).all(|i| p[i] >= self.min[i] && p[i] <= self.max[i])
+        }
+
+        pub fn contains_aabb(&self, other: &Aabb<T>) -> bool {
+                self.contains_point(other.min) && self.contains_point(other.max)
+        }
+
+        // touching boxes overlap
+        pub fn intersects_aabb(&self, other: &Aabb<T>) -> bool {
+                (0..3).all(|i| self.min[i] <= other.max[i] && other.min[i] <= self.max[i])
+        }
+
+        pub fn intersects_sphere(&self, sphere: &Sphere<T>) -> bool {
+                self.closest_point(sphere.center).distance(sphere.center) <= sphere.radius
+        }
+
+        // slab test, returns the entry and exit parameters with exit >= max(entry, 0),
+        // entry is negative when the ray starts inside
+        pub fn ray_interval(&self, ray: &Ray<T>) -> Option<(T, T)> {
+                self.slab(ray).map(|(t0, t1, _, _)| (t0, t1))
+        }
+
+        // entry, exit and the axes they happen on
+        fn slab(&self, ray: &Ray<T>) -> Option<(T, T, usize, usize)> {
+                let mut t0 = -infinity::<T>();
+                let mut t1 = infinity::<T>();
+                let (mut enter, mut exit) = (0, 0);
+                for i in 0..3 {
+                        let o = ray.origin[i];
+                        let d = ray.direction[i];
+                        if d == T::zero() {
+                                if o < self.min[i] || o > self.max[i] {
+                                        return None;
+                                }
+                                continue;
+                        }
+                        let inv = T::one() / d;
+                        let (mut near, mut far) = ((self.min[i] - o) * inv, (self.max[i] - o) * inv);
+                        if near > far {
+                                ::std::mem::swap(&mut near, &mut far);
+                        }
+                        if near > t0 {
+                                t0 = near;
+                                enter = i;
+                        }
+                        if far < t1 {
+                                t1 = far;
+                                exit = i;
+                        }
+                        if t0 > t1 {
+                                return None;
+                        }
+                }
+                if t1 < T::zero() {
+                        return None;
+                }
+                Some((t0, t1, enter, exit))
+        }
+
+        // first crossing of the box surface ahead of the ray origin (the exit when the
+        // ray starts inside) with the outward face normal there. None for a zero direction.
+        pub fn intersect_ray(&self, ray: &Ray<T>) -> Option<RayHit<T>> {
+                if ray.direction == Vector3::zero() {
+                        return None;
+                }
+                let (t0, t1, enter, exit) = self.slab(ray)?;
+                let (t, axis, sign) = if t0 >= T::zero() {
+                        (t0, enter, -ray.direction[enter].signum())
+                } else {
+                        (t1, exit, ray.direction[exit].signum())
+                };
+                let mut normal = Vector3::zero();
+                normal[axis] = sign;
+                Some(RayHit { t, point: ray.at(t), normal })
+        }
+}
+
+impl<T: Float> From<Aabb<T>> for Obb<T> {
+        fn from(b: Aabb<T>) -> Obb<T> {
+                Obb { center: b.center(), half_extents: b.half_extents(), rotation: Matrix3::identity() }
+        }
+}
+
+impl<T: Float> Obb<T> {
+        pub fn new(center: Vector3<T>, half_extents: Vector3<T>, rotation: Matrix3<T>) -> Obb<T> {
+                Obb { center, half_extents, rotation }
+        }
+
+        pub fn new_from_quat(center: Vector3<T>, half_extents: Vector3<T>, orientation: Quaternion<T>) -> Obb<T> {
+                Obb::new(center, half_extents, Matrix3::from(orientation.unitize()))
+        }
+
+        // box along the principal axes of the points (PCA). Not the minimal box,
+        // but close for elongated sets. None for an empty slice.
+        pub fn from_points(points: &[Vector3<T>]) -> Option<Obb<T>> {
+                if points.is_empty() {
+                        return None;
+                }
+                let rotation = principal_axes(points);
+                let local = points.iter().fold(Aabb::empty(), |b, p| b.grow(rotation.transpose() * *p));
+                Some(Obb { center: rotation * local.center(), half_extents: local.half_extents(), rotation })
+        }
+
+        pub fn orientation(&self) -> Quaternion<T> {
+                Quaternion::from(self.rotation)
+        }
+
+        pub fn axis(&self, i: usize) -> Vector3<T> {
+                self.rotation.col(i)
+        }
+
+        pub fn corners(&self) -> [Vector3<T>; 8] {
+                let local = Aabb::new(-self.half_extents, self.half_extents).corners();
+                let mut r = local;
+                for (c, l) in r.iter_mut().zip(local.iter()) {
+                        *c = self.rotation * *l + self.center;
+                }
+                r
+        }
+
+        pub fn aabb(&self) -> Aabb<T> {
+                let half = abs_matrix(self.rotation) * self.half_extents;
+                Aabb::new(self.center - half, self.center + half)
+        }
+
+        // PCA box around the corners of both
+        pub fn merge(&self, other: &Obb<T>) -> Obb<T> {
+                let mut points = self.corners().to_vec();
+                points.extend_from_slice(&other.corners());
+                Obb::from_points(&points).unwrap()
+        }
+
+        pub fn transform(&self, rotation: Matrix3<T>, translation: Vector3<T>) -> Obb<T> {
+                Obb {
+                        center: rotation * self.center + translation,
+                        half_extents: self.half_extents,
+                        rotation: rotation * self.rotation,
+                }
+        }
+
+        pub fn closest_point(&self, p: Vector3<T>) -> Vector3<T> {
+                let d = self.rotation.transpose() * (p - self.center);
+                let local = Aabb::new(-self.half_extents, self.half_extents).closest_point(d);
+                self.rotation * local + self.center
+        }
+
+        pub fn contains_point(&self, p: Vector3<T>) -> bool {
+                let d = self.rotation.transpose() * (p - self.center);
+                (0..3).all(|i| d[i].abs() <= self.half_extents[i])
+        }
+
+        pub fn intersects_sphere(&self, sphere: &Sphere<T>) -> bool {
+                self.closest_point(sphere.center).distance(sphere.center) <= sphere.radius
+        }
+
+        pub fn intersects_aabb(&self, other: &Aabb<T>) -> bool {
+                self.intersects_obb(&Obb::from(*other))
+        }
+
+        // separating axis test over the 15 candidate axes (Ericson 4.4.1)
+        pub fn intersects_obb(&self, other: &Obb<T>) -> bool {
+                let ea = self.half_extents;
+                let eb = other.half_extents;
+                // other's axes in self's frame
+                let r = self.rotation.transpose() * other.rotation;
+                let t = self.rotation.transpose() * (other.center - self.center);
+                // guards the edge-edge axes against near parallel axes
+                let mut abs_r = abs_matrix(r);
+                for e in abs_r.v.iter_mut() {
+                        *e += T::epsilon().sqrt();
+                }
+                let m = |i: usize, j: usize| r.v[i * 3 + j];
+                let a = |i: usize, j: usize| abs_r.v[i * 3 + j];
+
+                for i in 0..3 {
+                        let rb = eb[0] * a(i, 0) + eb[1] * a(i, 1) + eb[2] * a(i, 2);
+                        if t[i].abs() > ea[i] + rb {
+                                return false;
+                        }
+                }
+                for j in 0..3 {
+                        let ra = ea[0] * a(0, j) + ea[1] * a(1, j) + ea[2] * a(2, j);
+                        let tj = t[0] * m(0, j) + t[1] * m(1, j) + t[2] * m(2, j);
+                        if tj.abs() > ra + eb[j] {
+                                return false;
+                        }
+                }
+                for i in 0..3 {
+                        let (i1, i2) = ((i + 1) % 3, (i + 2) % 3);
+                        for j in 0..3 {
+                                let (j1, j2) = ((j + 1) % 3, (j + 2) % 3);
+                                let ra = ea[i1] * a(i2, j) + ea[i2] * a(i1, j);
+                                let rb = eb[j1] * a(i, j2) + eb[j2] * a(i, j1);
+                                let d = t[i2] * m(i1, j) - t[i1] * m(i2, j);
+                                if d.abs() > ra + rb {
+                                        return false;
+                                }
+                        }
+                }
+                true
+        }
+
+        // slab test in the box frame, same conventions as Aabb::intersect_ray
+        pub fn intersect_ray(&self, ray: &Ray<T>) -> Option<RayHit<T>> {
+                let to_local = self.rotation.transpose();
+                let local = Ray::new(to_local * (ray.origin - self.center), to_local * ray.direction);
+                let hit = Aabb::new(-self.half_extents, self.half_extents).intersect_ray(&local)?;
+                Some(RayHit { t: hit.t, point: ray.at(hit.t), normal: self.rotation * hit.normal })
+        }
+}
+
+impl<T: Float> Sphere<T> {
+        pub fn new(center: Vector3<T>, radius: T) -> Sphere<T> {
+                Sphere { center, radius }
+        }
+
+        // the minimal enclosing sphere (Welzl, move-to-front variant). The points are
+        // visited in a shuffled order from a fixed seed, so results are reproducible.
+        // None for an empty slice.
+        pub fn from_points(points: &[Vector3<T>]) -> Option<Sphere<T>> {
+                if points.is_empty() {
+                        return None;
+                }
+                let mut shuffled = points.to_vec();
+                let mut rng = Rng::new(0x5eed);
+                for i in (1..shuffled.len()).rev() {
+                        let j = (rng.next_u64() % (i as u64 + 1)) as usize;
+                        shuffled.swap(i, j);
+                }
+                let n = shuffled.len();
+                let mut support = Vec::with_capacity(4);
+                let sphere = welzl(&mut shuffled, n, &mut support);
+                // welzl accepts points within a small slack, widen to the farthest one
+                Some(sphere.fit_radius(points))
+        }
+
+        // radius raised to reach the farthest of points, so contains_point holds for every
+        // one of them despite round-off in the construction
+        fn fit_radius(&self, points: &[Vector3<T>]) -> Sphere<T> {
+                let radius = points.iter().fold(self.radius, |r, p| r.max(p.distance(self.center)));
+                Sphere { center: self.center, radius }
+        }
+
+        // Ritter's fast approximation, usually a few percent larger than the minimal sphere
+        pub fn from_points_ritter(points: &[Vector3<T>]) -> Option<Sphere<T>> {
+                let first = *points.first()?;
+                let farthest = |from: Vector3<T>| {
+                        points.iter().fold(from, |best, p| if p.distance(from) > best.distance(from) { *p } else { best })
+                };
+                let a = farthest(first);
+                let b = farthest(a);
+                let mut sphere = Sphere::new((a + b).scalar_mul(T::from_f64(0.5)), a.distance(b) * T::from_f64(0.5));
+                for p in points.iter() {
+                        sphere = sphere.grow(*p);
+                }
+                Some(sphere.fit_radius(points))
+        }
+
+        // the smallest sphere containing self and p
+        pub fn grow(&self, p: Vector3<T>) -> Sphere<T> {
+                let d = p.distance(self.center);
+                if d <= self.radius {
+                        return *self;
+                }
+                let radius = (self.radius + d) * T::from_f64(0.5);
+                let center = self.center + (p - self.center).scalar_mul((radius - self.radius) / d);
+                Sphere { center, radius }
+        }
+
+        pub fn merge(&self, other: &Sphere<T>) -> Sphere<T> {
+                let d = self.center.distance(other.center);
+                if d + other.radius <= self.radius {
+                        return *self;
+                }
+                if d + self.radius <= other.radius {
+                        return *other;
+                }
+                let radius = (d + self.radius + other.radius) * T::from_f64(0.5);
+                let center = self.center + (other.center - self.center).scalar_mul((radius - self.radius) / d);
+                Sphere { center, radius }
+        }
+
+        pub fn transform(&self, rotation: Matrix3<T>, translation: Vector3<T>) -> Sphere<T> {
+                Sphere { center: rotation * self.center + translation, radius: self.radius }
+        }
+
+        pub fn aabb(&self) -> Aabb<T> {
+                let r = Vector3::new(self.radius, self.radius, self.radius);
+                Aabb::new(self.center - r, self.center + r)
+        }
+
+        pub fn contains_point(&self, p: Vector3<T>) -> bool {
+                p.distance(self.center) <= self.radius
+        }
+
+        pub fn contains_sphere(&self, other: &Sphere<T>) -> bool {
+                self.center.distance(other.center) + other.radius <= self.radius
+        }
+
+        pub fn intersects_sphere(&self, other: &Sphere<T>) -> bool {
+                self.center.distance(other.center) <= self.radius + other.radius
+        }
+
+        pub fn intersects_aabb(&self, other: &Aabb<T>) -> bool {
+                other.intersects_sphere(self)
+        }
+
+        // first crossing of the surface ahead of the ray origin, outward normal
+        pub fn intersect_ray(&self, ray: &Ray<T>) -> Option<RayHit<T>> {
+                let (t0, t1) = ray_sphere(ray, self.center, self.radius)?;
+                let t = first_ahead(t0, t1)?;
+                let point = ray.at(t);
+                Some(RayHit { t, point, normal: (point - self.center).unitize() })
+        }
+}
+
+// move-to-front welzl over points[..n] with the given boundary points
+fn welzl<T: Float>(points: &mut Vec<Vector3<T>>, n: usize, support: &mut Vec<Vector3<T>>) -> Sphere<T> {
+        let mut sphere = sphere_from_support(support);
+        if support.len() == 4 {
+                return sphere;
+        }
+        for i in 0..n {
+                let p = points[i];
+                if !encloses(&sphere, p) {
+                        support.push(p);
+                        sphere = welzl(points, i, support);
+                        support.pop();
+                        let p = points.remove(i);
+                        points.insert(0, p);
+                }
+        }
+        sphere
+}
+
+// containment with some slack for round-off in the circumsphere
+fn encloses<T: Float>(sphere: &Sphere<T>, p: Vector3<T>) -> bool {
+        let slack = T::epsilon().sqrt() * (sphere.radius.max(T::zero()) + T::one());
+        p.distance(sphere.center) <= sphere.radius + slack
+}
+
+// smallest sphere with all support points on its surface. Affinely dependent
+// supports (round-off in welzl) fall back to the smallest sphere through a subset
+// that still encloses them all.
+fn sphere_from_support<T: Float>(s: &[Vector3<T>]) -> Sphere<T> {
+        let half = T::from_f64(0.5);
+        match s.len() {
+                0 => Sphere::new(Vector3::zero(), -T::one()),
+                1 => Sphere::new(s[0], T::zero()),
+                2 => Sphere::new((s[0] + s[1]).scalar_mul(half), s[0].distance(s[1]) * half),
+                3 => {
+                        let a = s[1] - s[0];
+                        let b = s[2] - s[0];
+                        let n = a.cross(&b);
+                        let nn = n.dot(&n);
+                        if nn <= T::epsilon() * a.dot(&a) * b.dot(&b) {
+                                return smallest_enclosing_subset(s);
+                        }
+                        let offset = (n.cross(&a).scalar_mul(b.dot(&b)) + b.cross(&n).scalar_mul(a.dot(&a)))
+                                .scalar_div(T::from_f64(2.0) * nn);
+                        Sphere::new(s[0] + offset, offset.length())
+                }
+                _ => {
+                        // |c - s_i|^2 equal for all i gives three linear equations
+                        let rows = [s[1] - s[0], s[2] - s[0], s[3] - s[0]];
+                        let m = Matrix3 { v: [
+                                rows[0].x(), rows[0].y(), rows[0].z(),
+                                rows[1].x(), rows[1].y(), rows[1].z(),
+                                rows[2].x(), rows[2].y(), rows[2].z() ] };
+                        let rhs = Vector3::new(rows[0].dot(&rows[0]), rows[1].dot(&rows[1]), rows[2].dot(&rows[2]))
+                                .scalar_mul(half);
+                        match m.solve(rhs) {
+                                Some(offset) => Sphere::new(s[0] + offset, offset.length()),
+                                None => smallest_enclosing_subset(s),
+                        }
+                }
+        }
+}
+
+fn smallest_enclosing_subset<T: Float>(s: &[Vector3<T>]) -> Sphere<T> {
+        let mut best: Option<Sphere<T>> = None;
+        let n = s.len();
+        for i in 0..n {
+                for j in (i + 1)..n {
+                        let mut candidates = vec![sphere_from_support(&[s[i], s[j]])];
+                        for k in (j + 1)..n {
+                                let sub = [s[i], s[j], s[k]];
+                                let a = s[j] - s[i];
+                                let b = s[k] - s[i];
+                                if a.cross(&b).dot(&a.cross(&b)) > T::epsilon() * a.dot(&a) * b.dot(&b) {
+                                        candidates.push(sphere_from_support(&sub));
+                                }
+                        }
+                        for c in candidates {
+                                let smaller = match best {
+                                        Some(b) => c.radius < b.radius,
+                                        None => true,
+                                };
+                                if smaller && s.iter().all(|p| encloses(&c, *p)) {
+                                        best = Some(c);
+                                }
+                        }
+                }
+        }
+        best.unwrap_or_else(|| sphere_from_support(&s[..1]))
+}
+
+impl<T: Float> Capsule<T> {
+        pub fn new(a: Vector3<T>, b: Vector3<T>, radius: T) -> Capsule<T> {
+                Capsule { segment: Segment::new(a, b), radius }
+        }
+
+        // segment along the principal axis spanning the projected points, radius the
+        // largest distance from that axis. Contains every point, not minimal.
+        pub fn from_points(points: &[Vector3<T>]) -> Option<Capsule<T>> {
+                if points.is_empty() {
+                        return None;
+                }
+                let mean = centroid(points);
+                let axis = principal_axes(points).col(0);
+                let inf = infinity::<T>();
+                let (lo, hi, radius) = points.iter().fold((inf, -inf, T::zero()), |(lo, hi, r), p| {
+                        let d = (*p - mean).dot(&axis);
+                        let off = (*p - mean - axis.scalar_mul(d)).length();
+                        (lo.min(d), hi.max(d), r.max(off))
+                });
+                let capsule = Capsule::new(mean + axis.scalar_mul(lo), mean + axis.scalar_mul(hi), radius);
+                // the distance to the segment can round above the perpendicular offset
+                let radius = points.iter().fold(radius, |r, p| r.max(capsule.segment.closest_point(*p).1.distance(*p)));
+                Some(Capsule { radius, ..capsule })
+        }
+
+        // capsule along the farthest pair of the four end points, wide enough for both
+        pub fn merge(&self, other: &Capsule<T>) -> Capsule<T> {
+                let ends = [self.segment.start, self.segment.end, other.segment.start, other.segment.end];
+                let mut best = (ends[0], ends[1]);
+                for i in 0..4 {
+                        for j in (i + 1)..4 {
+                                if ends[i].distance(ends[j]) > best.0.distance(best.1) {
+                                        best = (ends[i], ends[j]);
+                                }
+                        }
+                }
+                let segment = Segment::new(best.0, best.1);
+                // distance to a segment is convex, so the end points bound each capsule
+                let reach = |c: &Capsule<T>| {
+                        segment.closest_point(c.segment.start).1.distance(c.segment.start)
+                                .max(segment.closest_point(c.segment.end).1.distance(c.segment.end))
+                                + c.radius
+                };
+                Capsule { segment, radius: reach(self).max(reach(other)) }
+        }
+
+        pub fn transform(&self, rotation: Matrix3<T>, translation: Vector3<T>) -> Capsule<T> {
+                Capsule::new(
+                        rotation * self.segment.start + translation,
+                        rotation * self.segment.end + translation,
+                        self.radius)
+        }
+
+        pub fn aabb(&self) -> Aabb<T> {
+                Sphere::new(self.segment.start, self.radius).aabb()
+                        .merge(&Sphere::new(self.segment.end, self.radius).aabb())
+        }
+
+        pub fn contains_point(&self, p: Vector3<T>) -> bool {
+                self.segment.closest_point(p).1.distance(p) <= self.radius
+        }
+
+        pub fn intersects_sphere(&self, sphere: &Sphere<T>) -> bool {
+                self.segment.closest_point(sphere.center).1.distance(sphere.center) <= self.radius + sphere.radius
+        }
+
+        pub fn intersects_capsule(&self, other: &Capsule<T>) -> bool {
+                self.segment.distance(&other.segment) <= self.radius + other.radius
+        }
+
+        // first crossing of the surface ahead of the ray origin, outward normal.
+        // candidates are the cylinder wall between the end caps and the two cap spheres.
+        pub fn intersect_ray(&self, ray: &Ray<T>) -> Option<RayHit<T>> {
+                let a = self.segment.start;
+                let axis = self.segment.direction();
+                let aa = axis.dot(&axis);
+                let on_wall = |t: T| {
+                        let s = (ray.at(t) - a).dot(&axis);
+                        s >= T::zero() && s <= aa
+                };
+                let mut best: Option<T> = None;
+                let mut consider = |t: T| {
+                        let closer = match best {
+                                Some(b) => t < b,
+                                None => true,
+                        };
+                        if t >= T::zero() && closer {
+                                best = Some(t);
+                        }
+                };
+
+                if aa > T::zero() {
+                        // the ray and its offset with the axis components removed
+                        let flat = |v: Vector3<T>| v - axis.scalar_mul(v.dot(&axis) / aa);
+                        let flat_ray = Ray::new(flat(ray.origin - a), flat(ray.direction));
+                        if let Some((t0, t1)) = ray_sphere(&flat_ray, Vector3::zero(), self.radius) {
+                                for t in [t0, t1].iter() {
+                                        if on_wall(*t) {
+                                                consider(*t);
+                                        }
+                                }
+                        }
+                }
+                for (center, outside) in [(a, -T::one()), (self.segment.end, T::one())].iter() {
+                        if let Some((t0, t1)) = ray_sphere(ray, *center, self.radius) {
+                                for t in [t0, t1].iter() {
+                                        // only the half of each sphere beyond its end of the segment
+                                        let s = (ray.at(*t) - *center).dot(&axis) * *outside;
+                                        if s >= T::zero() || aa == T::zero() {
+                                                consider(*t);
+                                        }
+                                }
+                        }
+                }
+
+                let t = best?;
+                let point = ray.at(t);
+                let normal = (point - self.segment.closest_point(point).1).unitize();
+                Some(RayHit { t, point, normal })
+        }
+}
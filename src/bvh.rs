@@ -0,0 +1,440 @@
+use std::cmp::Ordering;
+
+use super::bounds::{Aabb, Capsule, Obb, Sphere};
+use super::primitive::Ray;
+use super::scalar::Float;
+use super::triangle::{Culling, Triangle, TriangleHit};
+use super::vector::Vector3;
+
+// anything a bvh can be built over
+pub trait Bounded<T: Float> {
+        fn aabb(&self) -> Aabb<T>;
+
+        // where the primitive sits for splitting, the box centre unless overridden
+        fn centroid(&self) -> Vector3<T> {
+                self.aabb().center()
+        }
+}
+
+impl<T: Float> Bounded<T> for Triangle<T> {
+        fn aabb(&self) -> Aabb<T> {
+                Aabb::empty().grow(self.a).grow(self.b).grow(self.c)
+        }
+
+        fn centroid(&self) -> Vector3<T> {
+                Triangle::centroid(self)
+        }
+}
+
+impl<T: Float> Bounded<T> for Aabb<T> {
+        fn aabb(&self) -> Aabb<T> {
+                *self
+        }
+}
+
+impl<T: Float> Bounded<T> for Sphere<T> {
+        fn aabb(&self) -> Aabb<T> {
+                Sphere::aabb(self)
+        }
+
+        fn centroid(&self) -> Vector3<T> {
+                self.center
+        }
+}
+
+impl<T: Float> Bounded<T> for Obb<T> {
+        fn aabb(&self) -> Aabb<T> {
+                Obb::aabb(self)
+        }
+
+        fn centroid(&self) -> Vector3<T> {
+                self.center
+        }
+}
+
+impl<T: Float> Bounded<T> for Capsule<T> {
+        fn aabb(&self) -> Aabb<T> {
+                Capsule::aabb(self)
+        }
+}
+
+impl<T: Float> Bounded<T> for Vector3<T> {
+        fn aabb(&self) -> Aabb<T> {
+                Aabb::new(*self, *self)
+        }
+}
+
+// nodes are stored depth first, so the left child of an inner node directly follows it.
+// count > 0 marks a leaf holding indices[first..first + count], an inner node
+// keeps the position of its right child in first.
+#[derive(Debug, Copy, Clone)]
+struct Node<T> {
+        bounds: Aabb<T>,
+        first: usize,
+        count: usize,
+}
+
+// bounding volume hierarchy over the boxes of a primitive slice. It stores primitive
+// indices only, queries hand those back and the caller tests the primitives themselves.
+#[derive(Debug, Clone)]
+pub struct Bvh<T> {
+        nodes: Vec<Node<T>>,
+        indices: Vec<usize>,
+        primitive_bounds: Vec<Aabb<T>>,
+}
+
+const BINS: usize = 16;
+const MAX_LEAF_SIZE: usize = 8;
+// cost of visiting a node relative to testing one primitive
+const TRAVERSAL_COST: f64 = 1.0;
+
+// the primitives' boxes and centroids, shared read-only by the builders
+struct BuildInput<'a, T: 'a> {
+        bounds: &'a [Aabb<T>],
+        centroids: &'a [Vector3<T>],
+}
+
+// surface area heuristic over binned centroids. Returns the axis and the bin boundary
+// to split at, or None when a leaf is cheaper or the centroids coincide.
+fn best_split<T: Float>(input: &BuildInput<T>, indices: &[usize], bounds: &Aabb<T>) -> Option<(usize, T, T)> {
+        // boxes without area (points along an axis-aligned line) leave nothing to weigh
+        let area = bounds.surface_area();
+        if area.is_nan() || area <= T::zero() {
+                return None;
+        }
+        let centroid_bounds = indices.iter().fold(Aabb::empty(), |b, i| b.grow(input.centroids[*i]));
+        let extent = centroid_bounds.size();
+        let leaf_cost = T::from_f64(indices.len() as f64);
+        let mut best: Option<(usize, T, T)> = None;
+
+        for axis in 0..3 {
+                if extent[axis] <= T::zero() {
+                        continue;
+                }
+                let lo = centroid_bounds.min[axis];
+                let scale = T::from_f64(BINS as f64) / extent[axis];
+                let bin_of = |i: usize| {
+                        let b = ((input.centroids[i][axis] - lo) * scale).to_f64() as usize;
+                        b.min(BINS - 1)
+                };
+                let mut bins = [(Aabb::empty(), 0usize); BINS];
+                for i in indices.iter() {
+                        let b = bin_of(*i);
+                        bins[b].0 = bins[b].0.merge(&input.bounds[*i]);
+                        bins[b].1 += 1;
+                }
+                // sweep from the right, then from the left evaluating each boundary
+                let mut right_area = [T::zero(); BINS];
+                let mut right_count = [0usize; BINS];
+                let mut acc = (Aabb::empty(), 0usize);
+                for b in (1..BINS).rev() {
+                        acc = (acc.0.merge(&bins[b].0), acc.1 + bins[b].1);
+                        right_area[b] = acc.0.surface_area();
+                        right_count[b] = acc.1;
+                }
+                let mut left = (Aabb::empty(), 0usize);
+                for b in 1..BINS {
+                        left = (left.0.merge(&bins[b - 1].0), left.1 + bins[b - 1].1);
+                        if left.1 == 0 || right_count[b] == 0 {
+                                continue;
+                        }
+                        let cost = T::from_f64(TRAVERSAL_COST)
+                                + (left.0.surface_area() * T::from_f64(left.1 as f64)
+                                        + right_area[b] * T::from_f64(right_count[b] as f64)) / area;
+                        let cheaper = match best {
+                                Some((_, _, c)) => cost < c,
+                                None => true,
+                        };
+                        if cheaper {
+                                best = Some((axis, lo + T::from_f64(b as f64) / scale, cost));
+                        }
+                }
+        }
+
+        match best {
+                Some(split) if split.2 < leaf_cost || indices.len() > MAX_LEAF_SIZE => Some(split),
+                _ => None,
+        }
+}
+
+// builds the subtree over indices, whose first element sits at base in the full index list.
+// node positions in the returned vector are relative to its start.
+fn build_subtree<T: Float>(input: &BuildInput<T>, indices: &mut [usize], base: usize) -> Vec<Node<T>> {
+        let bounds = indices.iter().fold(Aabb::empty(), |b, i| b.merge(&input.bounds[*i]));
+        let leaf = vec![Node { bounds, first: base, count: indices.len() }];
+        if indices.len() <= 1 {
+                return leaf;
+        }
+        let mid = match split_point(input, indices, &bounds) {
+                Some(mid) => mid,
+                None => return leaf,
+        };
+        let (left_indices, right_indices) = indices.split_at_mut(mid);
+        let left = build_subtree(input, left_indices, base);
+        let right = build_subtree(input, right_indices, base + mid);
+        join(bounds, left, right)
+}
+
+// partitions indices around the chosen split, None for a leaf
+fn split_point<T: Float>(input: &BuildInput<T>, indices: &mut [usize], bounds: &Aabb<T>) -> Option<usize> {
+        let (axis, split, _) = match best_split(input, indices, bounds) {
+                Some(s) => s,
+                // no usable split, a big leaf is still halved to keep leaves small
+                None if indices.len() > MAX_LEAF_SIZE => return Some(median_split(input, indices)),
+                None => return None,
+        };
+        let mut mid = 0;
+        for i in 0..indices.len() {
+                if input.centroids[indices[i]][axis] < split {
+                        indices.swap(i, mid);
+                        mid += 1;
+                }
+        }
+        if mid == 0 || mid == indices.len() {
+                return Some(median_split(input, indices));
+        }
+        Some(mid)
+}
+
+// halves indices at the median centroid along the axis where the centroids spread most
+fn median_split<T: Float>(input: &BuildInput<T>, indices: &mut [usize]) -> usize {
+        let extent = indices.iter().fold(Aabb::empty(), |b, i| b.grow(input.centroids[*i])).size();
+        let axis = if extent[0] >= extent[1] && extent[0] >= extent[2] { 0 } else if extent[1] >= extent[2] { 1 } else { 2 };
+        let mid = indices.len() / 2;
+        indices.select_nth_unstable_by(mid, |a, b| {
+                input.centroids[*a][axis].partial_cmp(&input.centroids[*b][axis]).unwrap_or(Ordering::Equal)
+        });
+        mid
+}
+
+fn join<T: Float>(bounds: Aabb<T>, left: Vec<Node<T>>, right: Vec<Node<T>>) -> Vec<Node<T>> {
+        let right_start = 1 + left.len();
+        let mut nodes = Vec::with_capacity(right_start + right.len());
+        nodes.push(Node { bounds, first: right_start, count: 0 });
+        let shift = |n: &Node<T>, offset: usize| if n.count == 0 { Node { first: n.first + offset, ..*n } } else { *n };
+        nodes.extend(left.iter().map(|n| shift(n, 1)));
+        nodes.extend(right.iter().map(|n| shift(n, right_start)));
+        nodes
+}
+
+#[cfg(feature = "parallel")]
+fn build_subtree_parallel<T: Float + Send + Sync>(input: &BuildInput<T>, indices: &mut [usize], base: usize, depth: usize) -> Vec<Node<T>> {
+        // below this many primitives threads cost more than they save
+        if depth == 0 || indices.len() < 4096 {
+                return build_subtree(input, indices, base);
+        }
+        let bounds = indices.iter().fold(Aabb::empty(), |b, i| b.merge(&input.bounds[*i]));
+        let mid = match split_point(input, indices, &bounds) {
+                Some(mid) => mid,
+                None => return vec![Node { bounds, first: base, count: indices.len() }],
+        };
+        let (left_indices, right_indices) = indices.split_at_mut(mid);
+        let (left, right) = ::std::thread::scope(|scope| {
+                let left = scope.spawn(|| build_subtree_parallel(input, left_indices, base, depth - 1));
+                let right = build_subtree_parallel(input, right_indices, base + mid, depth - 1);
+                (left.join().expect("bvh build thread panicked"), right)
+        });
+        join(bounds, left, right)
+}
+
+impl<T: Float> Bvh<T> {
+        pub fn build<P: Bounded<T>>(primitives: &[P]) -> Bvh<T> {
+                let bounds: Vec<Aabb<T>> = primitives.iter().map(|p| p.aabb()).collect();
+                let centroids: Vec<Vector3<T>> = primitives.iter().map(|p| p.centroid()).collect();
+                let mut indices: Vec<usize> = (0..primitives.len()).collect();
+                let nodes = if primitives.is_empty() {
+                        Vec::new()
+                } else {
+                        let input = BuildInput { bounds: &bounds, centroids: &centroids };
+                        build_subtree(&input, &mut indices, 0)
+                };
+                Bvh { nodes, indices, primitive_bounds: bounds }
+        }
+
+        // same tree as build, with the upper levels split across up to 2^depth threads
+        #[cfg(feature = "parallel")]
+        pub fn build_parallel<P: Bounded<T> + Sync>(primitives: &[P], depth: usize) -> Bvh<T>
+                where T: Send + Sync
+        {
+                let bounds: Vec<Aabb<T>> = primitives.iter().map(|p| p.aabb()).collect();
+                let centroids: Vec<Vector3<T>> = primitives.iter().map(|p| p.centroid()).collect();
+                let mut indices: Vec<usize> = (0..primitives.len()).collect();
+                let nodes = if primitives.is_empty() {
+                        Vec::new()
+                } else {
+                        let input = BuildInput { bounds: &bounds, centroids: &centroids };
+                        build_subtree_parallel(&input, &mut indices, 0, depth)
+                };
+                Bvh { nodes, indices, primitive_bounds: bounds }
+        }
+
+        // updates every box after the primitives moved, keeping the tree topology.
+        // cheap, but the tree degrades when primitives move far; rebuild then.
+        // primitives must be the same slice (same length and order) the tree was built over.
+        pub fn refit<P: Bounded<T>>(&mut self, primitives: &[P]) {
+                assert_eq!(primitives.len(), self.primitive_bounds.len(), "refit with a different number of primitives");
+                for (b, p) in self.primitive_bounds.iter_mut().zip(primitives.iter()) {
+                        *b = p.aabb();
+                }
+                // children always come after their parent
+                for n in (0..self.nodes.len()).rev() {
+                        let node = self.nodes[n];
+                        self.nodes[n].bounds = if node.count > 0 {
+                                self.indices[node.first..node.first + node.count].iter()
+                                        .fold(Aabb::empty(), |b, i| b.merge(&self.primitive_bounds[*i]))
+                        } else {
+                                self.nodes[n + 1].bounds.merge(&self.nodes[node.first].bounds)
+                        };
+                }
+        }
+
+        pub fn is_empty(&self) -> bool {
+                self.nodes.is_empty()
+        }
+
+        pub fn node_count(&self) -> usize {
+                self.nodes.len()
+        }
+
+        pub fn depth(&self) -> usize {
+                fn depth_of<T>(nodes: &[Node<T>], n: usize) -> usize {
+                        if nodes[n].count > 0 {
+                                1
+                        } else {
+                                1 + depth_of(nodes, n + 1).max(depth_of(nodes, nodes[n].first))
+                        }
+                }
+                if self.nodes.is_empty() { 0 } else { depth_of(&self.nodes, 0) }
+        }
+
+        // box around everything, None for an empty tree
+        pub fn bounds(&self) -> Option<Aabb<T>> {
+                self.nodes.first().map(|n| n.bounds)
+        }
+
+        // nearest hit along the ray. intersect(i, ray) tests primitive i and returns the
+        // ray parameter of its hit with whatever record it likes; subtrees farther than the
+        // best hit so far are skipped. Returns the primitive index, parameter and record.
+        pub fn closest_hit<H, F>(&self, ray: &Ray<T>, mut intersect: F) -> Option<(usize, T, H)>
+                where F: FnMut(usize, &Ray<T>) -> Option<(T, H)>
+        {
+                let mut best: Option<(usize, T, H)> = None;
+                let mut stack = Vec::with_capacity(64);
+                if let Some(t) = self.entry(0, ray) {
+                        stack.push((0, t));
+                }
+                while let Some((n, entry)) = stack.pop() {
+                        if let Some((_, t, _)) = best {
+                                if entry > t {
+                                        continue;
+                                }
+                        }
+                        let node = self.nodes[n];
+                        if node.count > 0 {
+                                for i in self.indices[node.first..node.first + node.count].iter() {
+                                        if let Some((t, hit)) = intersect(*i, ray) {
+                                                let closer = match best {
+                                                        Some((_, b, _)) => t < b,
+                                                        None => true,
+                                                };
+                                                if closer {
+                                                        best = Some((*i, t, hit));
+                                                }
+                                        }
+                                }
+                                continue;
+                        }
+                        // push the farther child first so the nearer one is visited next
+                        let children = [(n + 1, self.entry(n + 1, ray)), (node.first, self.entry(node.first, ray))];
+                        let (near, far) = match (children[0].1, children[1].1) {
+                                (Some(a), Some(b)) if b < a => (children[1], children[0]),
+                                _ => (children[0], children[1]),
+                        };
+                        for (c, t) in [far, near].iter() {
+                                if let Some(t) = t {
+                                        stack.push((*c, *t));
+                                }
+                        }
+                }
+                best
+        }
+
+        // any primitive hit with parameter at most max_t, for shadow and visibility rays.
+        // stops at the first one found, which need not be the nearest.
+        pub fn any_hit<F>(&self, ray: &Ray<T>, max_t: T, mut intersect: F) -> Option<usize>
+                where F: FnMut(usize, &Ray<T>) -> Option<T>
+        {
+                if self.nodes.is_empty() {
+                        return None;
+                }
+                let mut stack = vec![0];
+                while let Some(n) = stack.pop() {
+                        match self.entry(n, ray) {
+                                Some(t) if t <= max_t => {}
+                                _ => continue,
+                        }
+                        let node = self.nodes[n];
+                        if node.count > 0 {
+                                for i in self.indices[node.first..node.first + node.count].iter() {
+                                        if let Some(t) = intersect(*i, ray) {
+                                                if t <= max_t {
+                                                        return Some(*i);
+                                                }
+                                        }
+                                }
+                        } else {
+                                stack.push(node.first);
+                                stack.push(n + 1);
+                        }
+                }
+                None
+        }
+
+        // indices of the primitives whose boxes overlap the box
+        pub fn query_aabb(&self, region: &Aabb<T>) -> Vec<usize> {
+                self.query(|b| b.intersects_aabb(region))
+        }
+
+        // indices of the primitives whose boxes overlap the sphere
+        pub fn query_sphere(&self, sphere: &Sphere<T>) -> Vec<usize> {
+                self.query(|b| b.intersects_sphere(sphere))
+        }
+
+        fn query<F: Fn(&Aabb<T>) -> bool>(&self, overlaps: F) -> Vec<usize> {
+                let mut found = Vec::new();
+                if self.nodes.is_empty() {
+                        return found;
+                }
+                let mut stack = vec![0];
+                while let Some(n) = stack.pop() {
+                        let node = self.nodes[n];
+                        if !overlaps(&node.bounds) {
+                                continue;
+                        }
+                        if node.count > 0 {
+                                found.extend(self.indices[node.first..node.first + node.count].iter()
+                                        .filter(|i| overlaps(&self.primitive_bounds[**i])));
+                        } else {
+                                stack.push(node.first);
+                                stack.push(n + 1);
+                        }
+                }
+                found
+        }
+
+        // parameter where the ray enters node n (0 when it starts inside), None on a miss
+        fn entry(&self, n: usize, ray: &Ray<T>) -> Option<T> {
+                self.nodes.get(n)?.bounds.ray_interval(ray).map(|(t0, _)| t0.max(T::zero()))
+        }
+
+        // closest_hit for a bvh built over triangles
+        pub fn closest_triangle(&self, triangles: &[Triangle<T>], ray: &Ray<T>, culling: Culling) -> Option<(usize, TriangleHit<T>)> {
+                self.closest_hit(ray, |i, r| triangles[i].intersect_ray(r, culling).map(|h| (h.t, h)))
+                        .map(|(i, _, hit)| (i, hit))
+        }
+
+        // any_hit for a bvh built over triangles
+        pub fn any_triangle(&self, triangles: &[Triangle<T>], ray: &Ray<T>, max_t: T, culling: Culling) -> Option<usize> {
+                self.any_hit(ray, max_t, |i, r| triangles[i].intersect_ray(r, culling).map(|h| h.t))
+        }
+}
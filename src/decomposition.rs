@@ -0,0 +1,249 @@
+use super::matrix3::Matrix3;
+use super::matrix4::Matrix4;
+use super::matrix_n::Matrix;
+use super::scalar::Float;
+use super::vector::{Vector3, Vector4};
+use super::vector_n::VectorN;
+
+// self = rotation * stretch with stretch symmetric. A matrix with negative determinant
+// has no such split with a proper rotation; rotation is then the negated orthogonal
+// factor (still a proper rotation in 3d), stretch is negated to match and reflection is set.
+#[derive(Debug, Copy, Clone)]
+pub struct Polar<T> {
+        pub rotation: Matrix3<T>,
+        pub stretch: Matrix3<T>,
+        pub reflection: bool,
+}
+
+// the same dense algorithms for both sizes, on the row-major v arrays
+macro_rules! impl_decompositions {
+        ($mat:ident, $vec:ident, $n:expr) => {
+                #[allow(clippy::needless_range_loop)]
+                impl<T: Float> $mat<T> {
+                        // gaussian elimination with partial pivoting, returns (p, l, u) with
+                        // p * self = l * u, l unit lower triangular and u upper triangular.
+                        // a singular matrix leaves zeros on the diagonal of u.
+                        pub fn lu(&self) -> ($mat<T>, $mat<T>, $mat<T>) {
+                                let (p, l, u) = Matrix::<T, $n, $n>::from(*self).lu();
+                                ($mat::from(p), $mat::from(l), $mat::from(u))
+                        }
+
+                        // x with self * x = b through the lu factors, None when self is singular
+                        pub fn solve(&self, b: $vec<T>) -> Option<$vec<T>> {
+                                Matrix::<T, $n, $n>::from(*self).solve(VectorN::from(b)).map($vec::from)
+                        }
+
+                        // householder reflections, returns (q, r) with self = q * r,
+                        // q orthogonal and r upper triangular
+                        pub fn qr(&self) -> ($mat<T>, $mat<T>) {
+                                let n = $n;
+                                let two = T::from_f64(2.0);
+                                let mut r = *self;
+                                let mut q = $mat::identity();
+                                for c in 0..(n - 1) {
+                                        let norm = (c..n).fold(T::zero(), |s, i| s + r.v[i * n + c] * r.v[i * n + c]).sqrt();
+                                        if norm == T::zero() {
+                                                continue;
+                                        }
+                                        // reflect onto -sign(r_cc) * e_c to avoid cancellation
+                                        let alpha = if r.v[c * n + c] > T::zero() { -norm } else { norm };
+                                        let mut h = [T::zero(); $n];
+                                        for i in c..n {
+                                                h[i] = r.v[i * n + c];
+                                        }
+                                        h[c] -= alpha;
+                                        let hh = h.iter().fold(T::zero(), |s, e| s + *e * *e);
+                                        if hh == T::zero() {
+                                                continue;
+                                        }
+                                        for k in 0..n {
+                                                let s = (c..n).fold(T::zero(), |s, i| s + h[i] * r.v[i * n + k]) * two / hh;
+                                                for i in c..n {
+                                                        r.v[i * n + k] -= s * h[i];
+                                                }
+                                        }
+                                        for k in 0..n {
+                                                let s = (c..n).fold(T::zero(), |s, i| s + q.v[k * n + i] * h[i]) * two / hh;
+                                                for i in c..n {
+                                                        q.v[k * n + i] -= s * h[i];
+                                                }
+                                        }
+                                        for i in (c + 1)..n {
+                                                r.v[i * n + c] = T::zero();
+                                        }
+                                }
+                                (q, r)
+                        }
+
+                        // lower triangular l with self = l * l^T,
+                        // None unless self is symmetric positive definite
+                        pub fn cholesky(&self) -> Option<$mat<T>> {
+                                let n = $n;
+                                let mut l = $mat::zero();
+                                for j in 0..n {
+                                        let d = (0..j).fold(self.v[j * n + j], |d, k| d - l.v[j * n + k] * l.v[j * n + k]);
+                                        if d <= T::zero() || d.is_nan() {
+                                                return None;
+                                        }
+                                        let ljj = d.sqrt();
+                                        l.v[j * n + j] = ljj;
+                                        for i in (j + 1)..n {
+                                                let s = (0..j).fold(self.v[i * n + j], |s, k| s - l.v[i * n + k] * l.v[j * n + k]);
+                                                l.v[i * n + j] = s / ljj;
+                                        }
+                                }
+                                Some(l)
+                        }
+
+                        // cyclic jacobi for a symmetric matrix (only meaningful when self is symmetric).
+                        // returns the eigenvalues in descending order and the matching unit
+                        // eigenvectors as the columns of an orthogonal matrix.
+                        pub fn symmetric_eigen(&self) -> ([T; $n], $mat<T>) {
+                                let n = $n;
+                                let two = T::from_f64(2.0);
+                                let mut a = *self;
+                                let mut vecs = $mat::identity();
+                                let scale = a.v.iter().fold(T::zero(), |s, e| s + *e * *e);
+                                for _ in 0..50 {
+                                        let mut off = T::zero();
+                                        for p in 0..n {
+                                                for q in (p + 1)..n {
+                                                        off += a.v[p * n + q] * a.v[p * n + q];
+                                                }
+                                        }
+                                        if off <= scale * T::epsilon() * T::epsilon() {
+                                                break;
+                                        }
+                                        for p in 0..n {
+                                                for q in (p + 1)..n {
+                                                        let apq = a.v[p * n + q];
+                                                        if apq == T::zero() {
+                                                                continue;
+                                                        }
+                                                        let theta = (a.v[q * n + q] - a.v[p * n + p]) / (two * apq);
+                                                        let t = theta.signum() / (theta.abs() + (theta * theta + T::one()).sqrt());
+                                                        let c = T::one() / (t * t + T::one()).sqrt();
+                                                        let s = t * c;
+                                                        for k in 0..n {
+                                                                let (kp, kq) = (a.v[k * n + p], a.v[k * n + q]);
+                                                                a.v[k * n + p] = c * kp - s * kq;
+                                                                a.v[k * n + q] = s * kp + c * kq;
+                                                        }
+                                                        for k in 0..n {
+                                                                let (pk, qk) = (a.v[p * n + k], a.v[q * n + k]);
+                                                                a.v[p * n + k] = c * pk - s * qk;
+                                                                a.v[q * n + k] = s * pk + c * qk;
+                                                        }
+                                                        for k in 0..n {
+                                                                let (kp, kq) = (vecs.v[k * n + p], vecs.v[k * n + q]);
+                                                                vecs.v[k * n + p] = c * kp - s * kq;
+                                                                vecs.v[k * n + q] = s * kp + c * kq;
+                                                        }
+                                                }
+                                        }
+                                }
+
+                                // selection sort, swapping eigenvector columns along
+                                let mut values = [T::zero(); $n];
+                                for i in 0..n {
+                                        values[i] = a.v[i * n + i];
+                                }
+                                for i in 0..n {
+                                        let m = (i..n).fold(i, |m, j| if values[j] > values[m] { j } else { m });
+                                        if m != i {
+                                                values.swap(i, m);
+                                                for k in 0..n {
+                                                        vecs.v.swap(k * n + i, k * n + m);
+                                                }
+                                        }
+                                }
+                                (values, vecs)
+                        }
+                }
+        };
+}
+
+impl_decompositions!(Matrix3, Vector3, 3);
+impl_decompositions!(Matrix4, Vector4, 4);
+
+impl<T: Float> Matrix3<T> {
+        // singular value decomposition self = u * diag(s) * v^T with s descending and
+        // non-negative. u and v are orthogonal but may be reflections; flip the last
+        // column of both (and the sign of s[2]) when a proper rotation is needed.
+        // one-sided jacobi on the columns of self, so small singular values keep their
+        // relative accuracy instead of drowning in the squared condition number of self^T * self.
+        pub fn svd(&self) -> (Matrix3<T>, [T; 3], Matrix3<T>) {
+                let mut a = [self.col(0), self.col(1), self.col(2)];
+                let mut v = [Vector3::unit_x(), Vector3::new(T::zero(), T::one(), T::zero()), Vector3::unit_z()];
+                for _ in 0..32 {
+                        let mut rotated = false;
+                        for &(i, j) in [(0, 1), (0, 2), (1, 2)].iter() {
+                                let alpha = a[i].dot(&a[i]);
+                                let beta = a[j].dot(&a[j]);
+                                let gamma = a[i].dot(&a[j]);
+                                if gamma.abs() <= T::epsilon() * (alpha * beta).sqrt() {
+                                        continue;
+                                }
+                                // rotation making columns i and j orthogonal, the smaller root keeps it stable
+                                let zeta = (beta - alpha) / (T::from_f64(2.0) * gamma);
+                                let sign = if zeta < T::zero() { -T::one() } else { T::one() };
+                                let t = sign / (zeta.abs() + (T::one() + zeta * zeta).sqrt());
+                                let c = T::one() / (T::one() + t * t).sqrt();
+                                let s = c * t;
+                                let (ai, aj) = (a[i], a[j]);
+                                a[i] = ai.scalar_mul(c) - aj.scalar_mul(s);
+                                a[j] = ai.scalar_mul(s) + aj.scalar_mul(c);
+                                let (vi, vj) = (v[i], v[j]);
+                                v[i] = vi.scalar_mul(c) - vj.scalar_mul(s);
+                                v[j] = vi.scalar_mul(s) + vj.scalar_mul(c);
+                                rotated = true;
+                        }
+                        if !rotated {
+                                break;
+                        }
+                }
+                // the columns are now u * diag(s), sort them by length
+                let mut order = [0, 1, 2];
+                order.sort_by(|x, y| a[*y].length().partial_cmp(&a[*x].length()).unwrap_or(::std::cmp::Ordering::Equal));
+                let mut s = [T::zero(); 3];
+                for (s, o) in s.iter_mut().zip(order.iter()) {
+                        *s = a[*o].length();
+                }
+                let tolerance = s[0] * T::epsilon();
+                let mut u = [Vector3::zero(); 3];
+                for i in 0..3 {
+                        u[i] = if s[i] > tolerance && s[i] > T::zero() {
+                                a[order[i]].scalar_div(s[i])
+                        } else if i == 0 {
+                                Vector3::unit_x()
+                        } else if i == 1 {
+                                u[0].orthonormal_basis().0
+                        } else {
+                                u[0].cross(&u[1])
+                        };
+                }
+                // re-orthogonalize against round-off in the divided columns
+                let u0 = u[0].unitize();
+                let u1 = (u[1] - u0.scalar_mul(u0.dot(&u[1]))).unitize();
+                let u2 = if u[2].dot(&u0.cross(&u1)) < T::zero() { -u0.cross(&u1) } else { u0.cross(&u1) };
+                let v = Matrix3::new_from_cols(v[order[0]], v[order[1]], v[order[2]]);
+                (Matrix3::new_from_cols(u0, u1, u2), s, v)
+        }
+
+        // polar decomposition through the svd: rotation = u * v^T, stretch = v * diag(s) * v^T.
+        // stays well defined for singular matrices, unlike the newton iteration of orthonormalize_polar.
+        pub fn polar(&self) -> Polar<T> {
+                let (u, s, v) = self.svd();
+                let mut d = Matrix3::zero();
+                d.v[0] = s[0];
+                d.v[4] = s[1];
+                d.v[8] = s[2];
+                let rotation = u * v.transpose();
+                let stretch = v * d * v.transpose();
+                if rotation.determinant() < T::zero() {
+                        Polar { rotation: -rotation, stretch: -stretch, reflection: true }
+                } else {
+                        Polar { rotation, stretch, reflection: false }
+                }
+        }
+}
@@ -0,0 +1,192 @@
+use super::matrix3::Matrix3;
+use super::matrix4::Matrix4;
+use super::quaternion::Quaternion;
+use super::scalar::Float;
+use super::vector::Vector3;
+use std::convert::From;
+use std::ops::{Add, Mul};
+
+// rigid transform (rotation followed by translation) as real + eps * dual,
+// with real the rotation and dual = 0.5 * t * real.
+// unit dual quaternions compose with * like Mat4 does: (a * b) applies b first.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct DualQuaternion<T> {
+  real: Quaternion<T>,
+  dual: Quaternion<T>,
+}
+
+pub type DualQuat = DualQuaternion<f64>;
+pub type DualQuatf = DualQuaternion<f32>;
+
+impl<T: Float> Add for DualQuaternion<T> {
+  type Output = DualQuaternion<T>;
+  fn add(self, other: DualQuaternion<T>) -> DualQuaternion<T> {
+    DualQuaternion {
+      real: self.real + other.real,
+      dual: self.dual + other.dual,
+    }
+  }
+}
+
+impl<T: Float> Mul for DualQuaternion<T> {
+  type Output = DualQuaternion<T>;
+  fn mul(self, other: DualQuaternion<T>) -> DualQuaternion<T> {
+    DualQuaternion {
+      real: self.real * other.real,
+      dual: self.real * other.dual + self.dual * other.real,
+    }
+  }
+}
+
+impl<T: Float> Mul<T> for DualQuaternion<T> {
+  type Output = DualQuaternion<T>;
+  fn mul(self, s: T) -> DualQuaternion<T> {
+    DualQuaternion {
+      real: self.real.scalar_mul(s),
+      dual: self.dual.scalar_mul(s),
+    }
+  }
+}
+
+impl<T: Float> From<DualQuaternion<T>> for Matrix4<T> {
+  fn from(dq: DualQuaternion<T>) -> Matrix4<T> {
+    let dq = dq.unitize();
+    Matrix4::new_translation(dq.translation()) * Matrix4::from(Matrix3::from(dq.real))
+  }
+}
+
+// the upper-left block of m must be a pure rotation
+impl<T: Float> From<Matrix4<T>> for DualQuaternion<T> {
+  fn from(m: Matrix4<T>) -> DualQuaternion<T> {
+    DualQuaternion::new(Quaternion::from(m), m.translation())
+  }
+}
+
+impl<T: Float> DualQuaternion<T> {
+  pub fn identity() -> DualQuaternion<T> {
+    DualQuaternion {
+      real: Quaternion::new(T::one(), T::zero(), T::zero(), T::zero()),
+      dual: Quaternion::zero(),
+    }
+  }
+
+  // rotate first, then translate
+  pub fn new(rotation: Quaternion<T>, translation: Vector3<T>) -> DualQuaternion<T> {
+    let real = rotation.unitize();
+    let dual = (Quaternion::from(translation) * real).scalar_mul(T::from_f64(0.5));
+    DualQuaternion { real, dual }
+  }
+
+  pub fn new_from_parts(real: Quaternion<T>, dual: Quaternion<T>) -> DualQuaternion<T> {
+    DualQuaternion { real, dual }
+  }
+
+  pub fn real(&self) -> Quaternion<T> {
+    self.real
+  }
+
+  pub fn dual(&self) -> Quaternion<T> {
+    self.dual
+  }
+
+  pub fn rotation(&self) -> Quaternion<T> {
+    self.real
+  }
+
+  // t = 2 * dual * real^*
+  pub fn translation(&self) -> Vector3<T> {
+    Vector3::from(self.dual * self.real.conjugate()).scalar_mul(T::from_f64(2.0))
+  }
+
+  // quaternion conjugate of both parts, the inverse of a unit dual quaternion
+  pub fn conjugate(&self) -> DualQuaternion<T> {
+    DualQuaternion {
+      real: self.real.conjugate(),
+      dual: self.dual.conjugate(),
+    }
+  }
+
+  pub fn inverse(&self) -> DualQuaternion<T> {
+    self.unitize().conjugate()
+  }
+
+  // scales to a unit real part and removes the component of the dual part
+  // along the real part, so the result is a valid rigid transform again
+  pub fn unitize(&self) -> DualQuaternion<T> {
+    let length = self.real.length();
+    let real = self.real.scalar_mul(T::one() / length);
+    let dual = self.dual.scalar_mul(T::one() / length);
+    let dual = dual - real.scalar_mul(real.dot(&dual));
+    DualQuaternion { real, dual }
+  }
+
+  pub fn transform_point(&self, p: Vector3<T>) -> Vector3<T> {
+    self.real.rotate_vec3(p) + self.translation()
+  }
+
+  // directions only rotate
+  pub fn transform_vector(&self, d: Vector3<T>) -> Vector3<T> {
+    self.real.rotate_vec3(d)
+  }
+
+  // screw linear interpolation, constant speed along the screw motion from self to other
+  pub fn sclerp(&self, other: DualQuaternion<T>, t: T) -> DualQuaternion<T> {
+    let other = if self.real.dot(&other.real) < T::zero() { other * -T::one() } else { other };
+    *self * (self.conjugate() * other).powf(t)
+  }
+
+  // raises a unit dual quaternion to the power t by scaling its screw parameters
+  pub fn powf(&self, t: T) -> DualQuaternion<T> {
+    let half = T::from_f64(0.5);
+    let r = self.real;
+    let v = Vector3::from(r);
+    let sin_half = v.length();
+    let translation = self.translation();
+
+    // pure translation, the screw axis is undefined
+    if sin_half < T::epsilon().sqrt() {
+      return DualQuaternion::new(r.powf(t), translation.scalar_mul(t));
+    }
+
+    let angle = sin_half.atan2(r.w()) * T::from_f64(2.0);
+    let axis = v.scalar_div(sin_half);
+    let pitch = translation.dot(&axis);
+    let cot = (angle * half).cos() / sin_half;
+    let moment = (translation.cross(&axis) + (translation - axis.scalar_mul(pitch)).scalar_mul(cot))
+      .scalar_mul(half);
+
+    let angle = angle * t;
+    let pitch = pitch * t;
+    let (s, c) = ((angle * half).sin(), (angle * half).cos());
+    DualQuaternion {
+      real: Quaternion::new_from_vec(c, axis.scalar_mul(s)),
+      dual: Quaternion::new_from_vec(
+        -pitch * half * s,
+        moment.scalar_mul(s) + axis.scalar_mul(pitch * half * c),
+      ),
+    }
+  }
+
+  // dual quaternion linear blending (DLB) for skinning. Every input is flipped into the
+  // hemisphere of the first one before the weighted sum is unitized.
+  // returns None when dqs is empty or the weights sum to zero.
+  pub fn blend(dqs: &[DualQuaternion<T>], weights: &[T]) -> Option<DualQuaternion<T>> {
+    assert_eq!(dqs.len(), weights.len(), "one weight per dual quaternion");
+    let first = dqs.first()?.real;
+    if weights.iter().fold(T::zero(), |s, w| s + *w) == T::zero() {
+      return None;
+    }
+    let zero = DualQuaternion {
+      real: Quaternion::zero(),
+      dual: Quaternion::zero(),
+    };
+    let sum = dqs
+      .iter()
+      .zip(weights.iter())
+      .fold(zero, |sum, (dq, w)| {
+        let w = if first.dot(&dq.real) < T::zero() { -*w } else { *w };
+        sum + *dq * w
+      });
+    Some(sum.unitize())
+  }
+}
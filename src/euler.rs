@@ -0,0 +1,167 @@
+use super::matrix3::Matrix3;
+use super::quaternion::Quaternion;
+use super::scalar::Float;
+use super::vector::Vector3;
+
+// axis sequence of an euler angle triple.
+// the first six are Tait-Bryan (three distinct axes), the last six are
+// proper euler angles (first and last axis are the same).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EulerOrder {
+        XYZ,
+        XZY,
+        YXZ,
+        YZX,
+        ZXY,
+        ZYX,
+        XYX,
+        XZX,
+        YXY,
+        YZY,
+        ZXZ,
+        ZYZ,
+}
+
+// intrinsic rotations follow the rotating body axes, extrinsic ones the fixed world axes.
+// intrinsic XYZ with (a, b, c) is Rx(a) * Ry(b) * Rz(c),
+// extrinsic XYZ with (a, b, c) is Rz(c) * Ry(b) * Rx(a).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EulerFrame {
+        Intrinsic,
+        Extrinsic,
+}
+
+impl EulerOrder {
+        pub fn all() -> [EulerOrder; 12] {
+                [
+                        EulerOrder::XYZ, EulerOrder::XZY, EulerOrder::YXZ,
+                        EulerOrder::YZX, EulerOrder::ZXY, EulerOrder::ZYX,
+                        EulerOrder::XYX, EulerOrder::XZX, EulerOrder::YXY,
+                        EulerOrder::YZY, EulerOrder::ZXZ, EulerOrder::ZYZ,
+                ]
+        }
+
+        // axis indices, 0 = x, 1 = y, 2 = z
+        pub fn axes(self) -> (usize, usize, usize) {
+                match self {
+                        EulerOrder::XYZ => (0, 1, 2),
+                        EulerOrder::XZY => (0, 2, 1),
+                        EulerOrder::YXZ => (1, 0, 2),
+                        EulerOrder::YZX => (1, 2, 0),
+                        EulerOrder::ZXY => (2, 0, 1),
+                        EulerOrder::ZYX => (2, 1, 0),
+                        EulerOrder::XYX => (0, 1, 0),
+                        EulerOrder::XZX => (0, 2, 0),
+                        EulerOrder::YXY => (1, 0, 1),
+                        EulerOrder::YZY => (1, 2, 1),
+                        EulerOrder::ZXZ => (2, 0, 2),
+                        EulerOrder::ZYZ => (2, 1, 2),
+                }
+        }
+
+        pub fn reversed(self) -> EulerOrder {
+                match self {
+                        EulerOrder::XYZ => EulerOrder::ZYX,
+                        EulerOrder::XZY => EulerOrder::YZX,
+                        EulerOrder::YXZ => EulerOrder::ZXY,
+                        EulerOrder::YZX => EulerOrder::XZY,
+                        EulerOrder::ZXY => EulerOrder::YXZ,
+                        EulerOrder::ZYX => EulerOrder::XYZ,
+                        proper => proper,
+                }
+        }
+
+        pub fn is_proper(self) -> bool {
+                let (i, _, k) = self.axes();
+                i == k
+        }
+}
+
+fn unit_axis<T: Float>(i: usize) -> Vector3<T> {
+        let mut v = Vector3::zero();
+        v[i] = T::one();
+        v
+}
+
+// first axis, second axis and the remaining one, plus +1 when (i, j, k) is a
+// cyclic permutation of (x, y, z) and -1 otherwise
+fn axis_frame(order: EulerOrder) -> (usize, usize, usize, f64) {
+        let (i, j, _) = order.axes();
+        let k = 3 - i - j;
+        let parity = if (j + 3 - i) % 3 == 1 { 1.0 } else { -1.0 };
+        (i, j, k, parity)
+}
+
+impl<T: Float> Matrix3<T> {
+        pub fn new_from_euler(angles: (T, T, T), order: EulerOrder, frame: EulerFrame) -> Matrix3<T> {
+                let (i, j, k) = order.axes();
+                let ri = Matrix3::new_from_angle_axis(angles.0, unit_axis(i));
+                let rj = Matrix3::new_from_angle_axis(angles.1, unit_axis(j));
+                let rk = Matrix3::new_from_angle_axis(angles.2, unit_axis(k));
+                match frame {
+                        EulerFrame::Intrinsic => ri * rj * rk,
+                        EulerFrame::Extrinsic => rk * rj * ri,
+                }
+        }
+
+        // angles in the order of the sequence. The middle angle is in [-pi/2, pi/2]
+        // for Tait-Bryan orders and in [0, pi] for proper euler orders, the others in (-pi, pi].
+        // At gimbal lock only the sum (or difference) of the outer angles is defined;
+        // the third angle is then set to zero and the first one carries the whole rotation.
+        pub fn to_euler(&self, order: EulerOrder, frame: EulerFrame) -> (T, T, T) {
+                match frame {
+                        EulerFrame::Intrinsic => self.intrinsic_euler(order),
+                        EulerFrame::Extrinsic => {
+                                let (c, b, a) = self.intrinsic_euler(order.reversed());
+                                (a, b, c)
+                        }
+                }
+        }
+
+        fn intrinsic_euler(&self, order: EulerOrder) -> (T, T, T) {
+                let (i, j, k, parity) = axis_frame(order);
+                let s = T::from_f64(parity);
+                let m = |r: usize, c: usize| self.v[r * 3 + c];
+                let lock = T::epsilon().sqrt();
+
+                if order.is_proper() {
+                        let sin_b = (m(i, j).powi(2) + m(i, k).powi(2)).sqrt();
+                        let b = sin_b.atan2(m(i, i));
+                        if sin_b < lock {
+                                let a = (s * m(k, j)).atan2(m(j, j));
+                                return (a, b, T::zero());
+                        }
+                        let a = m(j, i).atan2(-s * m(k, i));
+                        let c = m(i, j).atan2(s * m(i, k));
+                        (a, b, c)
+                } else {
+                        let cos_b = (m(i, i).powi(2) + m(i, j).powi(2)).sqrt();
+                        let b = (s * m(i, k)).atan2(cos_b);
+                        if cos_b < lock {
+                                let a = (s * m(k, j)).atan2(m(j, j));
+                                return (a, b, T::zero());
+                        }
+                        let a = (-s * m(j, k)).atan2(m(k, k));
+                        let c = (-s * m(i, j)).atan2(m(i, i));
+                        (a, b, c)
+                }
+        }
+}
+
+impl<T: Float> Quaternion<T> {
+        pub fn new_from_euler(angles: (T, T, T), order: EulerOrder, frame: EulerFrame) -> Quaternion<T> {
+                let (i, j, k) = order.axes();
+                let qi = Quaternion::new_from_angle_axis(angles.0, unit_axis(i));
+                let qj = Quaternion::new_from_angle_axis(angles.1, unit_axis(j));
+                let qk = Quaternion::new_from_angle_axis(angles.2, unit_axis(k));
+                match frame {
+                        EulerFrame::Intrinsic => qi * qj * qk,
+                        EulerFrame::Extrinsic => qk * qj * qi,
+                }
+        }
+
+        // same ranges and gimbal lock behaviour as Matrix3::to_euler
+        pub fn to_euler(&self, order: EulerOrder, frame: EulerFrame) -> (T, T, T) {
+                Matrix3::from(self.unitize()).to_euler(order, frame)
+        }
+}
@@ -0,0 +1,282 @@
+use std::ops::Mul;
+use std::convert::From;
+use super::matrix_n::Matrix;
+use super::scalar::Float;
+use super::vector::Vector3;
+use super::vector_n::VectorN;
+
+// the 2d types mirror the 3d ones: VectorN<T, 2> plays Vector3, Matrix<T, 2, 2> plays
+// Matrix3, UnitComplex plays Quaternion and Affine2 plays Affine3.
+pub type Matrix2<T> = Matrix<T, 2, 2>;
+pub type Mat2 = Matrix2<f64>;
+pub type Mat2f = Matrix2<f32>;
+
+impl<T: Float> VectorN<T, 2> {
+        pub fn x(&self) -> T {
+                self.0[0]
+        }
+
+        pub fn y(&self) -> T {
+                self.0[1]
+        }
+
+        // rotated by +90 degrees
+        pub fn perp(&self) -> VectorN<T, 2> {
+                VectorN([-self.0[1], self.0[0]])
+        }
+
+        // z component of the 3d cross product, positive when other is counter-clockwise of self
+        pub fn cross(&self, other: &VectorN<T, 2>) -> T {
+                self.0[0] * other.0[1] - self.0[1] * other.0[0]
+        }
+
+        // signed angle from self to other in (-pi, pi]
+        pub fn angle(&self, other: &VectorN<T, 2>) -> T {
+                self.cross(other).atan2(self.dot(other))
+        }
+}
+
+impl<T: Float> Matrix<T, 2, 2> {
+        pub fn new_rotation(rad: T) -> Matrix<T, 2, 2> {
+                let (s, c) = (rad.sin(), rad.cos());
+                Matrix { v: [[c, -s], [s, c]] }
+        }
+
+        pub fn new_scale(s: VectorN<T, 2>) -> Matrix<T, 2, 2> {
+                Matrix { v: [[s.0[0], T::zero()], [T::zero(), s.0[1]]] }
+        }
+}
+
+// rotation in the plane as the unit complex number cos + i sin
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct UnitComplex<T> {
+        re: T,
+        im: T,
+}
+
+pub type Rot2 = UnitComplex<f64>;
+pub type Rot2f = UnitComplex<f32>;
+
+impl<T: Float> Mul for UnitComplex<T> {
+        type Output = UnitComplex<T>;
+
+        // (a * b) rotates by b, then by a
+        fn mul(self, o: UnitComplex<T>) -> UnitComplex<T> {
+                UnitComplex {
+                        re: self.re * o.re - self.im * o.im,
+                        im: self.re * o.im + self.im * o.re,
+                }
+        }
+}
+
+impl<T: Float> Mul<VectorN<T, 2>> for UnitComplex<T> {
+        type Output = VectorN<T, 2>;
+
+        fn mul(self, v: VectorN<T, 2>) -> VectorN<T, 2> {
+                self.rotate_vec2(v)
+        }
+}
+
+impl<T: Float> From<UnitComplex<T>> for Matrix<T, 2, 2> {
+        fn from(r: UnitComplex<T>) -> Matrix<T, 2, 2> {
+                Matrix { v: [[r.re, -r.im], [r.im, r.re]] }
+        }
+}
+
+// normalises (m00 + m11, m10 - m01), so scale is dropped and shear is averaged away
+impl<T: Float> From<Matrix<T, 2, 2>> for UnitComplex<T> {
+        fn from(m: Matrix<T, 2, 2>) -> UnitComplex<T> {
+                UnitComplex::new_from_parts(m.v[0][0] + m.v[1][1], m.v[1][0] - m.v[0][1])
+        }
+}
+
+impl<T: Float> UnitComplex<T> {
+        pub fn identity() -> UnitComplex<T> {
+                UnitComplex { re: T::one(), im: T::zero() }
+        }
+
+        pub fn new(rad: T) -> UnitComplex<T> {
+                UnitComplex { re: rad.cos(), im: rad.sin() }
+        }
+
+        // normalizes (re, im), the identity when both are zero
+        pub fn new_from_parts(re: T, im: T) -> UnitComplex<T> {
+                let length = (re * re + im * im).sqrt();
+                if length == T::zero() {
+                        return UnitComplex::identity();
+                }
+                UnitComplex { re: re / length, im: im / length }
+        }
+
+        // shortest rotation taking the direction of a onto the direction of b
+        pub fn rot_between_vecs(a: VectorN<T, 2>, b: VectorN<T, 2>) -> UnitComplex<T> {
+                UnitComplex::new_from_parts(a.dot(&b), a.cross(&b))
+        }
+
+        pub fn re(&self) -> T {
+                self.re
+        }
+
+        pub fn im(&self) -> T {
+                self.im
+        }
+
+        // in (-pi, pi]
+        pub fn angle(&self) -> T {
+                self.im.atan2(self.re)
+        }
+
+        pub fn inverse(&self) -> UnitComplex<T> {
+                UnitComplex { re: self.re, im: -self.im }
+        }
+
+        pub fn rotate_vec2(&self, v: VectorN<T, 2>) -> VectorN<T, 2> {
+                VectorN([self.re * v.0[0] - self.im * v.0[1], self.im * v.0[0] + self.re * v.0[1]])
+        }
+
+        // constant angular speed along the shorter arc
+        pub fn slerp(&self, other: UnitComplex<T>, t: T) -> UnitComplex<T> {
+                let delta = (self.inverse() * other).angle();
+                *self * UnitComplex::new(delta * t)
+        }
+}
+
+// 2d affine transform as a 3x3 homogeneous matrix with (0, 0, 1) as the last row,
+// built as translation * rotation * scale like Affine3
+#[derive(Debug, Clone, Copy)]
+pub struct Affine2<T> {
+        m: Matrix<T, 3, 3>,
+}
+
+pub type Transform2 = Affine2<f64>;
+pub type Transform2f = Affine2<f32>;
+
+impl<T: Float> Mul for Affine2<T> {
+        type Output = Affine2<T>;
+
+        // (a * b) applies b first, then a
+        fn mul(self, other: Affine2<T>) -> Affine2<T> {
+                Affine2 { m: self.m * other.m }
+        }
+}
+
+impl<T: Float> From<Affine2<T>> for Matrix<T, 3, 3> {
+        fn from(t: Affine2<T>) -> Matrix<T, 3, 3> {
+                t.m
+        }
+}
+
+impl<T: Float> Affine2<T> {
+        pub fn identity() -> Affine2<T> {
+                Affine2 { m: Matrix::identity() }
+        }
+
+        pub fn new(rotation: UnitComplex<T>, translation: VectorN<T, 2>, scale: VectorN<T, 2>) -> Affine2<T> {
+                Affine2::new_translation(translation) * Affine2::new_rotation(rotation) * Affine2::new_scale(scale)
+        }
+
+        pub fn new_linear(l: Matrix<T, 2, 2>) -> Affine2<T> {
+                let mut m = Matrix::identity();
+                for (row, l_row) in m.v.iter_mut().zip(l.v.iter()) {
+                        row[0] = l_row[0];
+                        row[1] = l_row[1];
+                }
+                Affine2 { m }
+        }
+
+        pub fn new_translation(t: VectorN<T, 2>) -> Affine2<T> {
+                let mut m = Matrix::identity();
+                m.v[0][2] = t.0[0];
+                m.v[1][2] = t.0[1];
+                Affine2 { m }
+        }
+
+        pub fn new_rotation(r: UnitComplex<T>) -> Affine2<T> {
+                Affine2::new_linear(Matrix::from(r))
+        }
+
+        pub fn new_scale(s: VectorN<T, 2>) -> Affine2<T> {
+                Affine2::new_linear(Matrix::new_scale(s))
+        }
+
+        // returns None for a non-affine matrix (last row other than (0, 0, 1))
+        pub fn try_from_mat3(m: Matrix<T, 3, 3>) -> Option<Affine2<T>> {
+                if m.v[2] == [T::zero(), T::zero(), T::one()] {
+                        Some(Affine2 { m })
+                } else {
+                        None
+                }
+        }
+
+        pub fn matrix(&self) -> Matrix<T, 3, 3> {
+                self.m
+        }
+
+        pub fn linear(&self) -> Matrix<T, 2, 2> {
+                Matrix { v: [[self.m.v[0][0], self.m.v[0][1]], [self.m.v[1][0], self.m.v[1][1]]] }
+        }
+
+        pub fn translation(&self) -> VectorN<T, 2> {
+                VectorN([self.m.v[0][2], self.m.v[1][2]])
+        }
+
+        pub fn try_inverse(&self) -> Option<Affine2<T>> {
+                let inv = self.linear().try_inverse()?;
+                let t = inv * self.translation();
+                Some(Affine2::new_translation(-t) * Affine2::new_linear(inv))
+        }
+
+        pub fn transform_point(&self, p: VectorN<T, 2>) -> VectorN<T, 2> {
+                self.linear() * p + self.translation()
+        }
+
+        // directions ignore the translation
+        pub fn transform_vector(&self, d: VectorN<T, 2>) -> VectorN<T, 2> {
+                self.linear() * d
+        }
+}
+
+// a plane in 3d with an orthonormal in-plane basis, for moving between
+// 3d points and 2d coordinates on the plane
+#[derive(Debug, Clone, Copy)]
+pub struct PlaneBasis<T> {
+        pub origin: Vector3<T>,
+        pub u: Vector3<T>,
+        pub v: Vector3<T>,
+}
+
+impl<T: Float> PlaneBasis<T> {
+        // some basis with u x v along normal
+        pub fn new(origin: Vector3<T>, normal: Vector3<T>) -> PlaneBasis<T> {
+                let (u, v) = normal.unitize().orthonormal_basis();
+                PlaneBasis { origin, u, v }
+        }
+
+        // u keeps its direction, v is made orthogonal to it within the plane of the two
+        pub fn new_from_axes(origin: Vector3<T>, u: Vector3<T>, v: Vector3<T>) -> PlaneBasis<T> {
+                let u = u.unitize();
+                let v = (v - u.scalar_mul(u.dot(&v))).unitize();
+                PlaneBasis { origin, u, v }
+        }
+
+        pub fn normal(&self) -> Vector3<T> {
+                self.u.cross(&self.v)
+        }
+
+        // in-plane coordinates of the orthogonal projection of p
+        pub fn project(&self, p: Vector3<T>) -> VectorN<T, 2> {
+                self.project_vector(p - self.origin)
+        }
+
+        pub fn project_vector(&self, d: Vector3<T>) -> VectorN<T, 2> {
+                VectorN([d.dot(&self.u), d.dot(&self.v)])
+        }
+
+        pub fn lift(&self, q: VectorN<T, 2>) -> Vector3<T> {
+                self.origin + self.lift_vector(q)
+        }
+
+        pub fn lift_vector(&self, d: VectorN<T, 2>) -> Vector3<T> {
+                self.u.scalar_mul(d.0[0]) + self.v.scalar_mul(d.0[1])
+        }
+}
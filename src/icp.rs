@@ -0,0 +1,214 @@
+use std::cmp::Ordering;
+
+use super::alignment;
+use super::dual_quaternion::DualQuaternion;
+use super::matrix4::Matrix4;
+use super::matrix_n::Matrix;
+use super::quaternion::Quaternion;
+use super::scalar::Float;
+use super::vector::Vector3;
+use super::vector_n::VectorN;
+
+#[derive(Debug, Copy, Clone)]
+pub struct IcpOptions<T> {
+        pub max_iterations: usize,
+        // pairs further apart than this are rejected as outliers
+        pub max_correspondence_distance: T,
+        // stops when an iteration moves the source by less than this (rotation in radians)
+        // and the error changes by less than it
+        pub tolerance: T,
+}
+
+impl<T: Float> Default for IcpOptions<T> {
+        fn default() -> IcpOptions<T> {
+                IcpOptions {
+                        max_iterations: 50,
+                        max_correspondence_distance: T::from_f64(f64::INFINITY),
+                        tolerance: T::from_f64(1e-6),
+                }
+        }
+}
+
+// rigid transform with target ~ rotation * source + translation
+#[derive(Debug, Copy, Clone)]
+pub struct IcpResult<T> {
+        pub rotation: Quaternion<T>,
+        pub translation: Vector3<T>,
+        pub iterations: usize,
+        pub converged: bool,
+        // root mean square residual over the inliers of the last correspondence search,
+        // point distances for point-to-point and distances along the normals for point-to-plane
+        pub rmse: T,
+        pub inliers: usize,
+}
+
+impl<T: Float> IcpResult<T> {
+        pub fn dual_quaternion(&self) -> DualQuaternion<T> {
+                DualQuaternion::new(self.rotation, self.translation)
+        }
+
+        pub fn matrix(&self) -> Matrix4<T> {
+                Matrix4::from(self.dual_quaternion())
+        }
+
+        pub fn transform_point(&self, p: Vector3<T>) -> Vector3<T> {
+                self.rotation.rotate_vec3(p) + self.translation
+        }
+}
+
+// balanced k-d tree over the target points. Built once per registration so each
+// correspondence search costs about log(m) instead of a scan over the whole target.
+struct KdTree<'a, T: 'a> {
+        points: &'a [Vector3<T>],
+        // the point splitting a range of order sits at its middle, the axis cycles x, y, z with depth
+        order: Vec<usize>,
+}
+
+impl<'a, T: Float> KdTree<'a, T> {
+        fn new(points: &'a [Vector3<T>]) -> KdTree<'a, T> {
+                let mut order: Vec<usize> = (0..points.len()).collect();
+                KdTree::split(points, &mut order, 0);
+                KdTree { points, order }
+        }
+
+        fn split(points: &[Vector3<T>], order: &mut [usize], axis: usize) {
+                if order.len() <= 1 {
+                        return;
+                }
+                let mid = order.len() / 2;
+                order.select_nth_unstable_by(mid, |a, b| {
+                        points[*a][axis].partial_cmp(&points[*b][axis]).unwrap_or(Ordering::Equal)
+                });
+                let (left, right) = order.split_at_mut(mid);
+                KdTree::split(points, left, (axis + 1) % 3);
+                KdTree::split(points, &mut right[1..], (axis + 1) % 3);
+        }
+
+        // index of the point closest to p, None when none is within max_sq (squared distance)
+        fn nearest(&self, p: Vector3<T>, max_sq: T) -> Option<usize> {
+                let mut best = (None, max_sq);
+                self.search(&self.order, 0, p, &mut best);
+                best.0
+        }
+
+        fn search(&self, order: &[usize], axis: usize, p: Vector3<T>, best: &mut (Option<usize>, T)) {
+                if order.is_empty() {
+                        return;
+                }
+                let mid = order.len() / 2;
+                let q = self.points[order[mid]];
+                let d = (q - p).dot(&(q - p));
+                if d < best.1 || (best.0.is_none() && d <= best.1) {
+                        *best = (Some(order[mid]), d);
+                }
+                let diff = p[axis] - q[axis];
+                let (near, far) = if diff < T::zero() { (&order[..mid], &order[mid + 1..]) } else { (&order[mid + 1..], &order[..mid]) };
+                self.search(near, (axis + 1) % 3, p, best);
+                if diff * diff <= best.1 {
+                        self.search(far, (axis + 1) % 3, p, best);
+                }
+        }
+}
+
+// index of the closest target point for every source point within max_distance
+fn correspondences<T: Float>(source: &[Vector3<T>], target: &KdTree<T>, max_distance: T) -> Vec<(usize, usize)> {
+        let max_sq = max_distance * max_distance;
+        source.iter().enumerate()
+                .filter_map(|(i, p)| target.nearest(*p, max_sq).map(|j| (i, j)))
+                .collect()
+}
+
+fn iterate<T: Float, F>(source: &[Vector3<T>], initial: DualQuaternion<T>, options: IcpOptions<T>, mut step: F) -> Option<IcpResult<T>>
+        where F: FnMut(&[Vector3<T>]) -> Option<(Quaternion<T>, Vector3<T>, T, usize)>
+{
+        let initial = initial.unitize();
+        let mut result = IcpResult {
+                rotation: initial.rotation(),
+                translation: initial.translation(),
+                iterations: 0,
+                converged: false,
+                rmse: T::from_f64(f64::INFINITY),
+                inliers: 0,
+        };
+        let mut moved: Vec<Vector3<T>> = source.iter().map(|p| result.transform_point(*p)).collect();
+        for _ in 0..options.max_iterations {
+                // a later step that loses its correspondences keeps the last good transform
+                let (dq, dt, rmse, inliers) = match step(&moved) {
+                        Some(s) => s,
+                        None if result.iterations == 0 => return None,
+                        None => break,
+                };
+                result.rotation = (dq * result.rotation).unitize();
+                result.translation = dq.rotate_vec3(result.translation) + dt;
+                result.iterations += 1;
+                result.inliers = inliers;
+                let change = (result.rmse - rmse).abs();
+                result.rmse = rmse;
+                for (m, p) in moved.iter_mut().zip(source.iter()) {
+                        *m = result.transform_point(*p);
+                }
+                let angle = dq.to_rotation_vector().length();
+                if angle < options.tolerance && dt.length() < options.tolerance && change < options.tolerance {
+                        result.converged = true;
+                        break;
+                }
+        }
+        Some(result)
+}
+
+// aligns source onto target by alternating closest point matching and Horn's closed form.
+// returns None when fewer than three pairs survive the rejection on the first iteration.
+pub fn point_to_point<T: Float>(source: &[Vector3<T>], target: &[Vector3<T>], initial: DualQuaternion<T>, options: IcpOptions<T>) -> Option<IcpResult<T>> {
+        let tree = KdTree::new(target);
+        iterate(source, initial, options, |moved| {
+                let pairs = correspondences(moved, &tree, options.max_correspondence_distance);
+                if pairs.len() < 3 {
+                        return None;
+                }
+                let from: Vec<Vector3<T>> = pairs.iter().map(|&(i, _)| moved[i]).collect();
+                let to: Vec<Vector3<T>> = pairs.iter().map(|&(_, j)| target[j]).collect();
+                // the error of the matches this step was computed from
+                let squared = from.iter().zip(to.iter()).fold(T::zero(), |s, (p, q)| s + (*q - *p).dot(&(*q - *p)));
+                let rmse = (squared / T::from_f64(pairs.len() as f64)).sqrt();
+                let a = alignment::align(&from, &to, false)?;
+                Some((a.rotation, a.translation, rmse, pairs.len()))
+        })
+}
+
+// minimizes the distance of source points to the tangent planes of their matches,
+// target_normals[j] being the unit normal at target[j]. Each step solves the small
+// angle linearization and applies it through the exponential map.
+// returns None when fewer than six pairs survive on the first iteration or the system is degenerate (e.g. a single plane).
+pub fn point_to_plane<T: Float>(source: &[Vector3<T>], target: &[Vector3<T>], target_normals: &[Vector3<T>], initial: DualQuaternion<T>, options: IcpOptions<T>) -> Option<IcpResult<T>> {
+        if target_normals.len() != target.len() {
+                return None;
+        }
+        let tree = KdTree::new(target);
+        iterate(source, initial, options, |moved| {
+                let pairs = correspondences(moved, &tree, options.max_correspondence_distance);
+                if pairs.len() < 6 {
+                        return None;
+                }
+                let mut a: Matrix<T, 6, 6> = Matrix::zero();
+                let mut b: VectorN<T, 6> = VectorN::zero();
+                let mut squared = T::zero();
+                for &(i, j) in pairs.iter() {
+                        let p = moved[i];
+                        let n = target_normals[j];
+                        let r = (p - target[j]).dot(&n);
+                        let c = p.cross(&n);
+                        let row = [c.x(), c.y(), c.z(), n.x(), n.y(), n.z()];
+                        for (k, a_row) in a.v.iter_mut().enumerate() {
+                                for (l, e) in a_row.iter_mut().enumerate() {
+                                        *e += row[k] * row[l];
+                                }
+                                b.0[k] -= row[k] * r;
+                        }
+                        squared += r * r;
+                }
+                let x = a.solve(b)?;
+                let rotation = Quaternion::new_from_rotation_vector(Vector3::new(x[0], x[1], x[2]));
+                let rmse = (squared / T::from_f64(pairs.len() as f64)).sqrt();
+                Some((rotation, Vector3::new(x[3], x[4], x[5]), rmse, pairs.len()))
+        })
+}
@@ -1,16 +1,49 @@
+pub mod alignment;
+pub mod average;
+pub mod bounds;
+pub mod bvh;
+pub mod decomposition;
+pub mod dual_quaternion;
+pub mod euler;
+pub mod geometry2d;
+pub mod icp;
+pub mod lie;
 pub mod matrix4;
 pub mod matrix3;
+pub mod matrix_n;
+pub mod primitive;
 pub mod quaternion;
+pub mod sampling;
+pub mod scalar;
+pub mod spline;
+pub mod transform;
+pub mod triangle;
 pub mod vector;
+pub mod vector_n;
 
 
 #[cfg(test)]
 mod tests {
 
-    use matrix4::Mat4;
-    use matrix3::Mat3;
-    use vector::{Angle, Vec3};
-    use quaternion::Quat;
+    use matrix4::{DepthRange, Handedness, Mat4, Mat4f};
+    use matrix3::{Mat3, Mat3f};
+    use vector::{Angle, Vec3, Vec3f, Vec4};
+    use quaternion::{Quat, Quatf};
+    use transform::{Transform, Trs};
+    use spline::QuatSpline;
+    use euler::{EulerFrame, EulerOrder};
+    use dual_quaternion::DualQuat;
+    use lie::{so3, se3, Twist};
+    use sampling::{self, Rng};
+    use alignment;
+    use icp::{self, IcpOptions};
+    use matrix_n::{Mat, Mat6, Matf};
+    use vector_n::{Vec2, Vec6, VecN};
+    use geometry2d::{Affine2, Mat2, PlaneBasis, Rot2, Transform2};
+    use primitive::{Line, Plane, Ray, Segment};
+    use triangle::{Culling, Triangle};
+    use bounds::{Aabb, Capsule, Obb, Sphere};
+    use bvh::{Bounded, Bvh};
 
     #[test]
     fn matrix4_multiply() {
@@ -43,6 +76,74 @@ mod tests {
     assert_eq!((m * n).v, v);
     }
 
+    #[test]
+    fn matrix4_determinant() {
+        let m = Mat4{v: [
+             1.0, 3.0,-2.0, 7.0,
+             2.0, 0.0, 3.0,-5.0,
+            -1.0, 2.0, 3.0, 1.0,
+             1.0, 3.0,-4.0, 7.0
+        ]};
+
+        assert!((m.determinant() - 94.0).abs() < 0.000001);
+        assert!((m.transpose().determinant() - m.determinant()).abs() < 0.000001);
+        assert_eq!(m.transpose().transpose().v, m.v);
+    }
+
+    #[test]
+    fn matrix4_inverse() {
+        let m = Mat4{v: [
+             1.0, 3.0,-2.0, 7.0,
+             2.0, 0.0, 3.0,-5.0,
+            -1.0, 2.0, 3.0, 1.0,
+             1.0, 3.0,-4.0, 7.0
+        ]};
+
+        let inv = m.try_inverse().unwrap();
+        let id = Mat4::identity();
+        for (a, b) in (m * inv).v.iter().zip(id.v.iter()) {
+            assert!((a - b).abs() < 0.000001);
+        }
+        for (a, b) in (inv * m).v.iter().zip(id.v.iter()) {
+            assert!((a - b).abs() < 0.000001);
+        }
+
+        // the adjugate is the inverse scaled by the determinant
+        let det = m.determinant();
+        for (a, b) in m.adjugate().v.iter().zip(inv.v.iter()) {
+            assert!((a - b * det).abs() < 0.000001);
+        }
+
+        // last row duplicates the first one
+        let singular = Mat4{v: [
+             1.0, 3.0,-2.0, 7.0,
+             2.0, 0.0, 3.0,-5.0,
+            -1.0, 2.0, 3.0, 1.0,
+             1.0, 3.0,-2.0, 7.0
+        ]};
+        assert!(singular.try_inverse().is_none());
+    }
+
+    #[test]
+    fn matrix4_inverse_small_and_large_scale() {
+        // well conditioned but with a determinant far below epsilon
+        let small = Mat4f::new_scale(Vec3f::new(0.004, 0.004, 0.004));
+        let inv = small.try_inverse().unwrap();
+        for (a, b) in (small * inv).v.iter().zip(Mat4f::identity().v.iter()) {
+            assert!((a - b).abs() < 0.0001);
+        }
+
+        // huge entries with a large determinant, yet rank deficient
+        let big = 1.0e6;
+        let singular = Mat4{v: [
+            big, 0.0, 0.0, 0.0,
+            0.0, big, 0.0, 0.0,
+            0.0, 0.0, big, big,
+            0.0, 0.0, big, big * (1.0 + 1.0e-17),
+        ]};
+        assert!(singular.try_inverse().is_none());
+    }
+
     #[test]
     fn rot_matrix1(){
         let p = Vec3::new(-1.0, 3.0, 2.0);
@@ -53,6 +154,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(unused_variables)]
     fn rot_matrix2(){
         let axis = Vec3::new(1.0, 1.0, 1.0);
         let rad = 45.0_f64.radians();
@@ -77,12 +179,337 @@ mod tests {
         // assert_eq!(rot_mat.v, v);
     }
 
+    #[test]
+    fn matrix3_inverse_small_scale() {
+        // det is 6.4e-8, below f32 epsilon, yet the matrix is perfectly conditioned
+        let r = Mat3f::new_from_angle_axis(0.3, Vec3f::new(1.0, 2.0, 3.0));
+        let mut small = r;
+        for e in small.v.iter_mut() {
+            *e *= 0.004;
+        }
+        let inv = small.try_inverse().unwrap();
+        for (a, b) in (small * inv).v.iter().zip(Mat3f::identity().v.iter()) {
+            assert!((a - b).abs() < 0.0001);
+        }
+
+        // the polar iteration must recover the rotation, not fall back to Gram-Schmidt
+        let mut skewed = small;
+        skewed.v[1] += 0.0004;
+        let polar = skewed.orthonormalize_polar();
+        let gram_schmidt = skewed.orthonormalize();
+        assert!(polar.is_rotation(0.0001));
+        assert!(polar.v.iter().zip(gram_schmidt.v.iter()).any(|(a, b)| (a - b).abs() > 0.00001));
+    }
+
+    #[test]
+    fn matrix3_inverse() {
+        let m = Mat3{v: [
+             2.0, 0.0, 1.0,
+             1.0, 3.0,-1.0,
+             0.0, 1.0, 4.0
+        ]};
+
+        assert!((m.determinant() - 27.0).abs() < 0.000001);
+        assert_eq!(m.trace(), 9.0);
+        assert_eq!(m.transpose().transpose().v, m.v);
+        assert_eq!(m.transpose().m01(), m.m10());
+
+        let id = Mat3::identity();
+        let inv = m.try_inverse().unwrap();
+        for (a, b) in (m * inv).v.iter().zip(id.v.iter()) {
+            assert!((a - b).abs() < 0.000001);
+        }
+
+        let singular = Mat3{v: [
+             1.0, 2.0, 3.0,
+             2.0, 4.0, 6.0,
+             0.0, 1.0, 4.0
+        ]};
+        assert!(singular.try_inverse().is_none());
+    }
+
+    #[test]
+    #[allow(clippy::assign_op_pattern)]
+    fn matrix3_orthonormalize() {
+        let axis = Vec3::new(-1.36712,2.55664,0.862798);
+        let q = Quat::new_from_angle_axis(30.0_f64.radians(), axis);
+        let m = Mat3::from(q);
+        assert!(m.is_rotation(0.000001));
+
+        // accumulate drift by repeated multiplication of a slightly perturbed step
+        let mut step = Mat3::new_from_angle_axis(1.0_f64.radians(), axis);
+        step.v[1] += 0.0001;
+        step.v[5] -= 0.0002;
+        let mut drifted = Mat3::identity();
+        for _ in 0..360 {
+            drifted = drifted * step;
+        }
+        assert!(!drifted.is_rotation(0.001));
+        assert!(drifted.orthonormalize().is_rotation(0.000001));
+        assert!(drifted.orthonormalize_polar().is_rotation(0.000001));
+
+        // an exact rotation is left untouched
+        for (a, b) in m.orthonormalize_polar().v.iter().zip(m.v.iter()) {
+            assert!((a - b).abs() < 0.000001);
+        }
+        for (a, b) in m.orthonormalize().v.iter().zip(m.v.iter()) {
+            assert!((a - b).abs() < 0.000001);
+        }
+    }
+
+    #[test]
+    fn transform_point_vector() {
+        let axis = Vec3::new(0.0, 0.0, 1.0);
+        let q = Quat::new_from_angle_axis(90.0_f64.radians(), axis);
+        let t = Transform::new_from_quat(q, Vec3::new(1.0, 2.0, 3.0), Vec3::new(2.0, 2.0, 2.0));
+
+        let p = Vec3::new(1.0, 0.0, 0.0);
+        assert!(t.transform_point(p).distance(Vec3::new(1.0, 4.0, 3.0)) < 0.000001);
+        assert!(t.transform_vector(p).distance(Vec3::new(0.0, 2.0, 0.0)) < 0.000001);
+
+        // normals stay perpendicular under non-uniform scale
+        let s = Transform::new_scale(Vec3::new(1.0, 4.0, 1.0));
+        let tangent = Vec3::new(1.0, -1.0, 0.0);
+        let normal = Vec3::new(1.0, 1.0, 0.0);
+        assert!(s.transform_vector(tangent).dot(&s.transform_normal(normal)).abs() < 0.000001);
+
+        // a singular transform still hands back a unit normal
+        let flat = Transform::new_scale(Vec3::new(1.0, 0.0, 1.0));
+        assert!((flat.transform_normal(normal.scalar_mul(3.0)).length() - 1.0).abs() < 0.000001);
+    }
+
+    #[test]
+    fn transform_compose_inverse() {
+        let a = Transform::new(
+            Mat3::new_from_angle_axis(30.0_f64.radians(), Vec3::new(1.0, 1.0, 0.0)),
+            Vec3::new(-1.0, 0.5, 2.0),
+            Vec3::new(1.0, 2.0, 3.0));
+        let b = Transform::new_from_quat(
+            Quat::new_from_angle_axis(-60.0_f64.radians(), Vec3::new(0.0, 1.0, 1.0)),
+            Vec3::new(4.0, 0.0, -2.0),
+            Vec3::new(0.5, 0.5, 0.5));
+
+        let p = Vec3::new(0.3, -2.0, 1.5);
+        let ab = a * b;
+        assert!(ab.transform_point(p).distance(a.transform_point(b.transform_point(p))) < 0.000001);
+
+        let inv = ab.try_inverse().unwrap();
+        assert!(inv.transform_point(ab.transform_point(p)).distance(p) < 0.000001);
+        let full = Mat4::from(ab).try_inverse().unwrap();
+        for (x, y) in inv.matrix().v.iter().zip(full.v.iter()) {
+            assert!((x - y).abs() < 0.000001);
+        }
+
+        assert!(Transform::new_scale(Vec3::new(1.0, 0.0, 1.0)).try_inverse().is_none());
+    }
+
+    #[test]
+    fn perspective_projection() {
+        let near = 0.5;
+        let far = 100.0;
+        let fov = 60.0_f64.radians();
+
+        let gl = Mat4::new_perspective(fov, 1.5, near, far, Handedness::Right, DepthRange::NegOneToOne);
+        let n = (gl * Vec4::new(0.0, 0.0, -near, 1.0)).perspective_divide().unwrap();
+        let f = (gl * Vec4::new(0.0, 0.0, -far, 1.0)).perspective_divide().unwrap();
+        assert!((n.z() + 1.0).abs() < 0.000001);
+        assert!((f.z() - 1.0).abs() < 0.000001);
+
+        // top edge of the frustum at the near plane
+        let top = near * (fov * 0.5).tan();
+        let t = (gl * Vec4::new(0.0, top, -near, 1.0)).perspective_divide().unwrap();
+        assert!((t.y() - 1.0).abs() < 0.000001);
+
+        let dx = Mat4::new_perspective(fov, 1.5, near, far, Handedness::Left, DepthRange::ZeroToOne);
+        let n = (dx * Vec4::new(0.0, 0.0, near, 1.0)).perspective_divide().unwrap();
+        let f = (dx * Vec4::new(0.0, 0.0, far, 1.0)).perspective_divide().unwrap();
+        assert!(n.z().abs() < 0.000001);
+        assert!((f.z() - 1.0).abs() < 0.000001);
+
+        // the infinite projection approaches 1 but never reaches it
+        let inf = Mat4::new_perspective_infinite(fov, 1.5, near, Handedness::Right, DepthRange::ZeroToOne);
+        let n = (inf * Vec4::new(0.0, 0.0, -near, 1.0)).perspective_divide().unwrap();
+        let f = (inf * Vec4::new(0.0, 0.0, -1.0e9, 1.0)).perspective_divide().unwrap();
+        assert!(n.z().abs() < 0.000001);
+        assert!(f.z() < 1.0 && f.z() > 0.999999);
+
+        assert!(Vec4::new(1.0, 2.0, 3.0, 0.0).perspective_divide().is_none());
+    }
+
+    #[test]
+    fn orthographic_projection() {
+        let m = Mat4::new_orthographic(-2.0, 4.0, -1.0, 1.0, 1.0, 11.0, Handedness::Right, DepthRange::NegOneToOne);
+        let a = (m * Vec4::new_point(Vec3::new(-2.0, -1.0, -1.0))).perspective_divide().unwrap();
+        let b = (m * Vec4::new_point(Vec3::new(4.0, 1.0, -11.0))).perspective_divide().unwrap();
+        assert!(a.distance(Vec3::new(-1.0, -1.0, -1.0)) < 0.000001);
+        assert!(b.distance(Vec3::new(1.0, 1.0, 1.0)) < 0.000001);
+
+        let m = Mat4::new_orthographic(-2.0, 4.0, -1.0, 1.0, 1.0, 11.0, Handedness::Left, DepthRange::ZeroToOne);
+        let a = (m * Vec4::new_point(Vec3::new(-2.0, -1.0, 1.0))).perspective_divide().unwrap();
+        let b = (m * Vec4::new_point(Vec3::new(4.0, 1.0, 11.0))).perspective_divide().unwrap();
+        assert!(a.distance(Vec3::new(-1.0, -1.0, 0.0)) < 0.000001);
+        assert!(b.distance(Vec3::new(1.0, 1.0, 1.0)) < 0.000001);
+    }
+
+    #[test]
+    fn look_at_view() {
+        let eye = Vec3::new(3.0, 4.0, 5.0);
+        let target = Vec3::new(1.0, 1.0, 1.0);
+        let up = Vec3::new(0.0, 1.0, 0.0);
+        let dist = eye.distance(target);
+
+        let rh = Mat4::new_look_at(eye, target, up, Handedness::Right);
+        assert!(rh.transform_point(eye).length() < 0.000001);
+        assert!(rh.transform_point(target).distance(Vec3::new(0.0, 0.0, -dist)) < 0.000001);
+        assert!(rh.linear().is_rotation(0.000001));
+
+        let lh = Mat4::new_look_at(eye, target, up, Handedness::Left);
+        assert!(lh.transform_point(target).distance(Vec3::new(0.0, 0.0, dist)) < 0.000001);
+
+        // up stays up on screen
+        let above = target + up;
+        assert!(rh.transform_point(above).y() > 0.0);
+        assert!(lh.transform_point(above).y() > 0.0);
+    }
+
+    #[test]
+    fn single_precision() {
+        let axis = Vec3f::new(-1.36712, 2.55664, 0.862798);
+        let start = Vec3f::new(0.393636, -0.271898, 0.863048);
+        let end = Vec3f::new(0.754185, -0.00259262, 0.63634);
+
+        let q = Quatf::new_from_angle_axis(30.0_f32.radians(), axis);
+        assert!(q.rotate_vec3(start).distance(end) < 0.0001);
+        assert!((axis.unitize().length() - 1.0).abs() < 0.000001);
+
+        let m = Mat3f::from(q);
+        assert!(start.apply_rot_mat3(m).distance(end) < 0.0001);
+        let ypr = m.yaw_pitch_roll();
+        let m01 = ypr.0.sin() * ypr.1.sin() * ypr.2.cos() - ypr.0.cos() * ypr.2.sin();
+        assert!((m.m01() - m01).abs() < 0.0001);
+
+        let inv = Mat4f::from(m).try_inverse().unwrap();
+        assert!(inv.linear().is_rotation(0.0001));
+    }
+
+    #[test]
+    fn precision_conversion() {
+        let v = Vec3f::new(0.1, -2.5, 3.0);
+        let wide = Vec3::from(v);
+        assert_eq!(wide.x(), 0.1_f32 as f64);
+        assert_eq!(wide.cast::<f32>(), v);
+
+        let q = Quat::new_from_angle_axis(0.3, Vec3::new(1.0, 2.0, 3.0));
+        let narrow: Quatf = q.cast();
+        assert!((Quat::from(narrow).w() - q.w()).abs() < 0.000001);
+
+        let m = Mat3::new_from_angle_axis(0.3, Vec3::new(1.0, 2.0, 3.0));
+        let round_trip = Mat3::from(m.cast::<f32>());
+        for (a, b) in round_trip.v.iter().zip(m.v.iter()) {
+            assert!((a - b).abs() < 0.000001);
+        }
+    }
+
     #[test]
     fn vector_cross() {
         let a = Vec3::new(-1.0, 1.0, 2.0);
         let b = Vec3::new(2.0, 3.0, -2.0);
 
-        assert_eq!(a * b, Vec3::new(-8.0, 2.0, -5.0) );
+        assert_eq!(a.cross(&b), Vec3::new(-8.0, 2.0, -5.0) );
+    }
+
+    #[test]
+    fn vector_operators() {
+        let a = Vec3::new(-1.0, 1.0, 2.0);
+        let b = Vec3::new(2.0, 3.0, -2.0);
+
+        assert_eq!(-a, Vec3::new(1.0, -1.0, -2.0));
+        assert_eq!(a * 2.0, Vec3::new(-2.0, 2.0, 4.0));
+        assert_eq!(2.0 * a, a * 2.0);
+        assert_eq!(a / 2.0, Vec3::new(-0.5, 0.5, 1.0));
+
+        let mut c = a;
+        c += b;
+        assert_eq!(c, a + b);
+        c -= b;
+        assert_eq!(c, a);
+        c *= 3.0;
+        assert_eq!(c, a.scalar_mul(3.0));
+        c /= 3.0;
+        assert_eq!(c, a);
+
+        c[2] = 5.0;
+        assert_eq!((c[0], c[1], c[2]), (-1.0, 1.0, 5.0));
+
+        let points = vec![a, b, Vec3::new(2.0, -1.0, 3.0)];
+        assert_eq!(points.iter().sum::<Vec3>(), Vec3::new(3.0, 3.0, 3.0));
+        assert_eq!(points.into_iter().sum::<Vec3>(), Vec3::new(3.0, 3.0, 3.0));
+
+        let v = Vec4::new(1.0, 2.0, 3.0, 4.0);
+        assert_eq!(-v * 2.0, Vec4::new(-2.0, -4.0, -6.0, -8.0));
+        assert_eq!(v[3], 4.0);
+    }
+
+    #[test]
+    fn matrix_operators() {
+        let axis = Vec3::new(-1.36712,2.55664,0.862798);
+        let m = Mat3::new_from_angle_axis(30.0_f64.radians(), axis);
+        let p = Vec3::new(0.393636,-0.271898,0.863048);
+
+        assert_eq!(m * p, p.apply_rot_mat3(m));
+        assert_eq!(m[(1, 2)], m.m12());
+
+        let mut n = Mat3::identity();
+        n *= m;
+        assert_eq!(n.v, m.v);
+        n[(0, 0)] = 7.0;
+        assert_eq!(n.m00(), 7.0);
+
+        assert_eq!((m + m).v, (m * 2.0).v);
+        assert_eq!((m - m).v, Mat3::zero().v);
+        assert_eq!((-m).v, (m * -1.0).v);
+
+        let t = Mat4::new_translation(p);
+        assert_eq!(t[(2, 3)], p.z());
+        assert_eq!((t - t).v, Mat4::zero().v);
+    }
+
+    #[test]
+    fn matrix_mul_assign() {
+        let axis = Vec3::new(-1.36712,2.55664,0.862798);
+        let step = Mat3::new_from_angle_axis(1.0_f64.radians(), axis);
+        let mut accumulated = Mat3::identity();
+        for _ in 0..10 {
+            accumulated *= step;
+        }
+        let product = (0..10).fold(Mat3::identity(), |p, _| p * step);
+        assert_eq!(accumulated.v, product.v);
+
+        let t = Mat4::new_translation(Vec3::new(1.0, 2.0, 3.0));
+        let mut m = Mat4::identity();
+        m *= t;
+        m *= t;
+        assert_eq!(m.v, (t * t).v);
+    }
+
+    #[test]
+    fn quat_operators() {
+        let axis = Vec3::new(-1.36712,2.55664,0.862798);
+        let q = Quat::new_from_angle_axis(30.0_f64.radians(), axis);
+        let p = Vec3::new(0.393636,-0.271898,0.863048);
+
+        assert_eq!(q * p, q.rotate_vec3(p));
+        assert!((-q * p).distance(q * p) < 0.000001);
+        assert_eq!(q - q, Quat::zero());
+        assert_eq!(q * 2.0, q + q);
+        assert_eq!((q * 2.0) / 2.0, q);
+
+        let mut r = q;
+        r *= q;
+        assert_eq!(r, q * q);
+        r -= q;
+        r += q;
+        assert!((r - q * q).length() < 0.000001);
     }
 
     #[test]
@@ -156,6 +583,147 @@ mod tests {
         assert!(q.rotate_vec3(start).distance(start.apply_rot_mat3(m)) < 0.001);
     }
 
+    #[test]
+    fn quat_slerp() {
+        let axis = Vec3::new(-1.36712,2.55664,0.862798);
+        let a = Quat::new_from_angle_axis(10.0_f64.radians(), axis);
+        let b = Quat::new_from_angle_axis(130.0_f64.radians(), axis);
+
+        assert!((a.slerp(b, 0.0) - a).length() < 0.000001);
+        assert!((a.slerp(b, 1.0) - b).length() < 0.000001);
+
+        // constant speed along the arc
+        let q = Quat::new_from_angle_axis(40.0_f64.radians(), axis);
+        assert!((a.slerp(b, 0.25) - q).length() < 0.000001);
+
+        // -b is the same rotation, slerp still takes the short way
+        assert!((a.slerp(-b, 0.25) - q).length() < 0.000001);
+
+        let n = a.nlerp(-b, 0.5);
+        assert!((n.length() - 1.0).abs() < 0.000001);
+        let mid = Quat::new_from_angle_axis(70.0_f64.radians(), axis);
+        assert!((n - mid).length() < 0.000001);
+    }
+
+    #[test]
+    fn quat_squad_spline() {
+        let keys = vec![
+            Quat::new_from_angle_axis(0.0, Vec3::new(0.0, 0.0, 1.0)),
+            Quat::new_from_angle_axis(60.0_f64.radians(), Vec3::new(0.0, 1.0, 1.0)),
+            -Quat::new_from_angle_axis(100.0_f64.radians(), Vec3::new(1.0, 0.0, 0.0)),
+            Quat::new_from_angle_axis(45.0_f64.radians(), Vec3::new(1.0, 1.0, 1.0)),
+        ];
+        let spline = QuatSpline::new(&keys);
+
+        // passes through every key (up to sign)
+        for (i, k) in keys.iter().enumerate() {
+            let s = spline.sample(i as f64);
+            assert!(s.dot(k).abs() > 0.999999);
+        }
+
+        // stays on the unit sphere and moves smoothly across a key
+        let h = 0.0001;
+        for &t in [0.3, 1.0, 1.7, 2.0, 2.5].iter() {
+            let s = spline.sample(t);
+            assert!((s.length() - 1.0).abs() < 0.000001);
+            let before = (s - spline.sample(t - h)).length();
+            let after = (spline.sample(t + h) - s).length();
+            assert!((before - after).abs() < 0.00001);
+        }
+
+        // squad with its own endpoints as controls reduces to slerp
+        let a = keys[0];
+        let b = keys[1];
+        assert!((Quat::squad(a, a, b, b, 0.3) - a.slerp(b, 0.3)).length() < 0.000001);
+    }
+
+    #[test]
+    fn quat_exp_ln() {
+        let axis = Vec3::new(-1.36712,2.55664,0.862798);
+        let q = Quat::new_from_angle_axis(30.0_f64.radians(), axis);
+
+        // ln of a unit quaternion is (0, axis * angle / 2)
+        let l = q.ln();
+        assert!(l.w().abs() < 0.000001);
+        assert!(Vec3::from(l).distance(axis.unitize().scalar_mul(15.0_f64.radians())) < 0.000001);
+        assert!((l.exp() - q).length() < 0.000001);
+
+        // non-unit quaternions round trip too
+        let p = Quat::new(3.0, 2.0, -1.0, -2.0);
+        assert!((p.ln().exp() - p).length() < 0.000001);
+
+        let half = Quat::new_from_angle_axis(15.0_f64.radians(), axis);
+        assert!((q.powf(0.5) - half).length() < 0.000001);
+        assert!((q.powf(2.0) - q * q).length() < 0.000001);
+
+        let identity = Quat::new(1.0, 0.0, 0.0, 0.0);
+        assert_eq!(identity.ln(), Quat::zero());
+        assert_eq!(Quat::zero().exp(), identity);
+    }
+
+    #[test]
+    fn quat_ln_negative_real() {
+        // angle pi, the log has magnitude pi and exp brings back -1
+        let minus_one = Quat::new(-1.0, 0.0, 0.0, 0.0);
+        let l = minus_one.ln();
+        assert!(l.w().abs() < 0.000001);
+        assert!((Vec3::from(l).length() - ::std::f64::consts::PI).abs() < 0.000001);
+        assert!((l.exp() - minus_one).length() < 0.000001);
+
+        // a square root of -1 is a half turn, not the identity
+        let root = minus_one.powf(0.5);
+        assert!(root.w().abs() < 0.000001);
+        assert!((root * root - minus_one).length() < 0.000001);
+
+        // tiny vector parts keep their direction, and ln stays continuous
+        let nearly = Quat::new(-1.0, 1.0e-17, 0.0, 0.0);
+        assert!(Vec3::from(nearly.ln()).distance(Vec3::new(::std::f64::consts::PI, 0.0, 0.0)) < 0.000001);
+        let close = Quat::new(-1.0, 1.0e-9, 0.0, 0.0);
+        assert!((close.ln() - nearly.ln()).length() < 0.000001);
+        assert!((nearly.ln().exp() - nearly).length() < 0.000001);
+
+        // non-unit negative reals round trip with their norm
+        let scaled = Quat::new(-2.5, 0.0, 0.0, 0.0);
+        assert!((scaled.ln().exp() - scaled).length() < 0.000001);
+    }
+
+    #[test]
+    fn quat_rotation_vector() {
+        let axis = Vec3::new(-1.36712,2.55664,0.862798);
+        let q = Quat::new_from_angle_axis(30.0_f64.radians(), axis);
+
+        let r = q.to_rotation_vector();
+        assert!((r.length() - 30.0_f64.radians()).abs() < 0.000001);
+        assert!((Quat::new_from_rotation_vector(r) - q).length() < 0.000001);
+
+        // -q is the same rotation, the vector is the same
+        assert!((-q).to_rotation_vector().distance(r) < 0.000001);
+
+        // the long way round is reported as the short rotation
+        let long = Quat::new_from_angle_axis(300.0_f64.radians(), axis);
+        let r = long.to_rotation_vector();
+        assert!((r.length() - 60.0_f64.radians()).abs() < 0.000001);
+        assert!(r.dot(&axis) < 0.0);
+
+        let identity = Quat::new(1.0, 0.0, 0.0, 0.0);
+        assert_eq!(identity.to_rotation_vector(), Vec3::zero());
+        assert_eq!(Quat::new_from_rotation_vector(Vec3::zero()), identity);
+    }
+
+    #[test]
+    fn quat_angle_axis() {
+        let axis = Vec3::new(-1.36712,2.55664,0.862798);
+        let q = Quat::new_from_angle_axis(30.0_f64.radians(), axis);
+        let (angle, a) = q.scalar_mul(3.0).angle_axis();
+        assert!((angle - 30.0_f64.radians()).abs() < 0.000001);
+        assert!(a.distance(axis.unitize()) < 0.000001);
+
+        let (angle, a) = Quat::new(1.0, 0.0, 0.0, 0.0).angle_axis();
+        assert_eq!(angle, 0.0);
+        assert!(!a.x().is_nan() && !a.y().is_nan() && !a.z().is_nan());
+        assert!((a.length() - 1.0).abs() < 0.000001);
+    }
+
     #[test]
     fn yaw_pitch_roll() {
         let axis = Vec3::new(-1.36712,2.55664,0.862798);
@@ -186,4 +754,1317 @@ mod tests {
             (e.2.cos() * e.1.cos() * e.0.cos() - e.2.sin() * e.0.sin()))
             .abs() < 0.00001);
     }
+
+    fn mat3_close(a: Mat3, b: Mat3, tolerance: f64) -> bool {
+        a.v.iter().zip(b.v.iter()).all(|(x, y)| (x - y).abs() < tolerance)
+    }
+
+    #[test]
+    fn euler_round_trip() {
+        let angles = (0.3, -1.1, 2.5);
+        for &frame in [EulerFrame::Intrinsic, EulerFrame::Extrinsic].iter() {
+            for &order in EulerOrder::all().iter() {
+                // proper euler angles keep the middle angle in [0, pi]
+                let angles = if order.is_proper() { (angles.0, 1.1, angles.2) } else { angles };
+                let m = Mat3::new_from_euler(angles, order, frame);
+                assert!(m.is_rotation(0.000001));
+
+                let e = m.to_euler(order, frame);
+                assert!((e.0 - angles.0).abs() < 0.000001, "{:?} {:?}", order, frame);
+                assert!((e.1 - angles.1).abs() < 0.000001, "{:?} {:?}", order, frame);
+                assert!((e.2 - angles.2).abs() < 0.000001, "{:?} {:?}", order, frame);
+
+                let q = Quat::new_from_euler(angles, order, frame);
+                assert!(mat3_close(Mat3::from(q), m, 0.000001));
+                let e = q.to_euler(order, frame);
+                assert!((e.1 - angles.1).abs() < 0.000001);
+            }
+        }
+
+        // intrinsic XYZ is extrinsic ZYX with the angles reversed
+        let a = Mat3::new_from_euler((0.1, 0.2, 0.3), EulerOrder::XYZ, EulerFrame::Intrinsic);
+        let b = Mat3::new_from_euler((0.3, 0.2, 0.1), EulerOrder::ZYX, EulerFrame::Extrinsic);
+        assert!(mat3_close(a, b, 0.000001));
+    }
+
+    #[test]
+    fn euler_gimbal_lock() {
+        let half_pi = 90.0_f64.radians();
+        for &frame in [EulerFrame::Intrinsic, EulerFrame::Extrinsic].iter() {
+            for &order in EulerOrder::all().iter() {
+                for &b in [half_pi, -half_pi, 0.0, 180.0_f64.radians()].iter() {
+                    // locked middle angle: +-pi/2 for Tait-Bryan, 0 or pi for proper
+                    if order.is_proper() == (b.abs() == half_pi) {
+                        continue;
+                    }
+                    let m = Mat3::new_from_euler((0.4, b, -0.7), order, frame);
+                    let e = m.to_euler(order, frame);
+                    assert!(!e.0.is_nan() && !e.1.is_nan() && !e.2.is_nan());
+                    let back = Mat3::new_from_euler(e, order, frame);
+                    assert!(mat3_close(back, m, 0.000001), "{:?} {:?} {}", order, frame, b);
+                }
+            }
+        }
+
+        // the legacy helpers no longer blow up at the singularity
+        let m = Mat3::new_from_euler((0.4, half_pi, -0.7), EulerOrder::ZYX, EulerFrame::Intrinsic);
+        let ypr = m.yaw_pitch_roll();
+        assert!((ypr.1 - half_pi).abs() < 0.000001);
+        assert!(!ypr.0.is_nan() && !ypr.2.is_nan());
+        let zyz = Mat3::identity().eular_angle_zyz();
+        assert_eq!(zyz, (0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn mat3_to_quat() {
+        let axes = [
+            Vec3::new(-1.36712,2.55664,0.862798),
+            Vec3::new(1.0, 0.0, 0.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            Vec3::new(0.0, 0.0, 1.0),
+            Vec3::new(1.0, -1.0, 0.5),
+        ];
+        // angles around pi exercise every branch of the diagonal selection
+        let angles = [0.0, 30.0, 90.0, 150.0, 179.0, 180.0];
+
+        for axis in axes.iter() {
+            for angle in angles.iter() {
+                let q = Quat::new_from_angle_axis(angle.radians(), *axis);
+                let m = Mat3::new_from_angle_axis(angle.radians(), *axis);
+                let r = Quat::from(m);
+
+                assert!(r.w() >= 0.0);
+                assert!((r.length() - 1.0).abs() < 0.000001);
+                // same rotation, q and -q are equivalent
+                assert!(r.dot(&q).abs() > 0.999999, "{} {}", axis, angle);
+                assert!(mat3_close(Mat3::from(r), m, 0.000001));
+            }
+        }
+
+        let q = Quat::new_from_angle_axis(75.0_f64.radians(), Vec3::new(1.0, 2.0, 3.0));
+        let t = Transform::new_from_quat(q, Vec3::new(4.0, 5.0, 6.0), Vec3::new(1.0, 1.0, 1.0));
+        assert!(Quat::from(Mat4::from(t)).dot(&q).abs() > 0.999999);
+    }
+
+    #[test]
+    fn dual_quat_transform() {
+        let q = Quat::new_from_angle_axis(75.0_f64.radians(), Vec3::new(1.0, 2.0, 3.0));
+        let t = Vec3::new(4.0, -5.0, 6.0);
+        let dq = DualQuat::new(q, t);
+        let p = Vec3::new(0.3, -2.0, 1.5);
+
+        assert!(dq.translation().distance(t) < 0.000001);
+        assert!(dq.transform_point(p).distance(q.rotate_vec3(p) + t) < 0.000001);
+        assert!(dq.transform_vector(p).distance(q.rotate_vec3(p)) < 0.000001);
+        assert!(dq.inverse().transform_point(dq.transform_point(p)).distance(p) < 0.000001);
+
+        // matches the equivalent matrix both ways
+        let m = Mat4::from(dq);
+        assert!(m.transform_point(p).distance(dq.transform_point(p)) < 0.000001);
+        let back = DualQuat::from(m);
+        assert!(back.transform_point(p).distance(dq.transform_point(p)) < 0.000001);
+
+        // composition applies the right hand side first
+        let other = DualQuat::new(Quat::new_from_angle_axis(-0.4, Vec3::new(0.0, 1.0, 0.0)), Vec3::new(1.0, 0.0, 0.0));
+        let composed = dq * other;
+        assert!(composed.transform_point(p).distance(dq.transform_point(other.transform_point(p))) < 0.000001);
+        let mat = Mat4::from(dq) * Mat4::from(other);
+        assert!(mat.transform_point(p).distance(composed.transform_point(p)) < 0.000001);
+    }
+
+    #[test]
+    fn dual_quat_blending() {
+        let axis = Vec3::new(0.0, 0.0, 1.0);
+        let a = DualQuat::new(Quat::new_from_angle_axis(0.0, axis), Vec3::new(0.0, 0.0, 0.0));
+        let b = DualQuat::new(Quat::new_from_angle_axis(90.0_f64.radians(), axis), Vec3::new(0.0, 0.0, 2.0));
+
+        assert!((a.sclerp(b, 0.0).real() - a.real()).length() < 0.000001);
+        assert!(a.sclerp(b, 1.0).translation().distance(b.translation()) < 0.000001);
+
+        // half way along a screw about z: half the angle, half the pitch
+        let mid = a.sclerp(b, 0.5);
+        let half = Quat::new_from_angle_axis(45.0_f64.radians(), axis);
+        assert!(mid.real().dot(&half).abs() > 0.999999);
+        assert!(mid.translation().distance(Vec3::new(0.0, 0.0, 1.0)) < 0.000001);
+
+        // sign of the input does not matter
+        let flipped = DualQuat::new_from_parts(-b.real(), -b.dual());
+        let mid_flipped = a.sclerp(flipped, 0.5);
+        let p = Vec3::new(1.0, 0.0, 0.0);
+        assert!(mid_flipped.transform_point(p).distance(mid.transform_point(p)) < 0.000001);
+
+        // blending keeps the rotation rigid, unlike averaging matrices
+        let blended = DualQuat::blend(&[a, flipped], &[0.5, 0.5]).unwrap();
+        assert!((blended.real().length() - 1.0).abs() < 0.000001);
+        assert!(blended.real().dot(&half).abs() > 0.999999);
+        assert!(Mat4::from(blended).linear().is_rotation(0.000001));
+        assert!(DualQuat::blend(&[a, b], &[0.0, 0.0]).is_none());
+        assert!(DualQuat::blend(&[], &[]).is_none());
+
+        // pure translations interpolate linearly
+        let c = DualQuat::new(Quat::new(1.0, 0.0, 0.0, 0.0), Vec3::new(2.0, 4.0, 6.0));
+        assert!(a.sclerp(c, 0.25).translation().distance(Vec3::new(0.5, 1.0, 1.5)) < 0.000001);
+    }
+
+    fn mat4_close(a: Mat4, b: Mat4, tolerance: f64) -> bool {
+        a.v.iter().zip(b.v.iter()).all(|(x, y)| (x - y).abs() < tolerance)
+    }
+
+    #[test]
+    fn so3_exp_log() {
+        let w = Vec3::new(0.3, -0.8, 1.1);
+        let r = so3::exp(w);
+        assert!(r.is_rotation(0.000001));
+        assert!(so3::log(r).distance(w) < 0.000001);
+        assert!(mat3_close(Mat3::from(so3::exp_quat(w)), r, 0.000001));
+        assert!(so3::log_quat(so3::exp_quat(w)).distance(w) < 0.000001);
+
+        let v = Vec3::new(2.0, 1.0, -1.0);
+        assert!((so3::hat(w) * v).distance(w.cross(&v)) < 0.000001);
+        assert_eq!(so3::vee(so3::hat(w)), w);
+
+        // close to pi the log is still accurate
+        let w = Vec3::new(1.0, 2.0, 2.0).unitize().scalar_mul(179.9_f64.radians());
+        assert!(so3::log(so3::exp(w)).distance(w) < 0.000001);
+
+        let tiny = Vec3::new(1.0e-10, -2.0e-10, 0.5e-10);
+        assert!(so3::log(so3::exp(tiny)).distance(tiny) < 1.0e-15);
+    }
+
+    #[test]
+    fn so3_jacobians() {
+        let h = 1.0e-6;
+        for w in [Vec3::new(0.3, -0.8, 1.1), Vec3::new(1.0e-9, 0.0, 0.0)].iter() {
+            let w = *w;
+            let jl = so3::left_jacobian(w);
+            let jr = so3::right_jacobian(w);
+            assert!(mat3_close(jl * so3::left_jacobian_inv(w), Mat3::identity(), 0.000001));
+            assert!(mat3_close(jr * so3::right_jacobian_inv(w), Mat3::identity(), 0.000001));
+
+            // exp(w + dw) = exp(jl dw) exp(w) = exp(w) exp(jr dw)
+            for i in 0..3 {
+                let mut dw = Vec3::zero();
+                dw[i] = h;
+                let lhs = so3::exp(w + dw);
+                assert!(mat3_close(lhs, so3::exp(jl * dw) * so3::exp(w), 1.0e-10));
+                assert!(mat3_close(lhs, so3::exp(w) * so3::exp(jr * dw), 1.0e-10));
+            }
+        }
+    }
+
+    #[test]
+    fn se3_exp_log() {
+        let xi = Twist::new(Vec3::new(1.0, -2.0, 0.5), Vec3::new(0.3, -0.8, 1.1));
+        let m = se3::exp(xi);
+        assert!(m.linear().is_rotation(0.000001));
+        let back = se3::log(m);
+        assert!(back.rho.distance(xi.rho) < 0.000001);
+        assert!(back.phi.distance(xi.phi) < 0.000001);
+        assert!(mat4_close(se3::hat(se3::vee(se3::hat(xi))), se3::hat(xi), 1.0e-12));
+
+        // pure translation
+        let t = Twist::new(Vec3::new(1.0, 2.0, 3.0), Vec3::zero());
+        assert!(se3::exp(t).translation().distance(t.rho) < 0.000001);
+
+        // adjoint moves a twist between frames
+        let a = se3::exp(Twist::new(Vec3::new(-0.5, 0.2, 1.0), Vec3::new(0.1, 0.4, -0.3)));
+        let lhs = a * se3::exp(xi) * a.try_inverse().unwrap();
+        assert!(mat4_close(lhs, se3::exp(se3::adjoint(a) * xi), 0.000001));
+    }
+
+    #[test]
+    fn se3_jacobians() {
+        let h = 1.0e-6;
+        let xis = [
+            Twist::new(Vec3::new(1.0, -2.0, 0.5), Vec3::new(0.3, -0.8, 1.1)),
+            Twist::new(Vec3::new(1.0, -2.0, 0.5), Vec3::new(1.0e-9, 0.0, 0.0)),
+        ];
+        for xi in xis.iter() {
+            let xi = *xi;
+            let jl = se3::left_jacobian(xi);
+            let jr = se3::right_jacobian(xi);
+            let il = jl * se3::left_jacobian_inv(xi);
+            for r in 0..6 {
+                for c in 0..6 {
+                    let expected = if r == c { 1.0 } else { 0.0 };
+                    assert!((il.get(r, c) - expected).abs() < 0.000001);
+                }
+            }
+
+            for i in 0..6 {
+                let mut d = [0.0; 6];
+                d[i] = h;
+                let d = Twist::from_array(d);
+                let lhs = se3::exp(xi + d);
+                assert!(mat4_close(lhs, se3::exp(jl * d) * se3::exp(xi), 1.0e-10));
+                assert!(mat4_close(lhs, se3::exp(xi) * se3::exp(jr * d), 1.0e-10));
+            }
+        }
+    }
+
+    #[test]
+    fn lie_jacobians_small_angle() {
+        let rho = Vec3::new(1.0, -2.0, 0.5);
+        let dir = Vec3::new(0.6, -0.8, 0.0);
+        for theta in [2.0e-8, 1.0e-7, 1.0e-3, 0.05, 0.2].iter() {
+            let theta = *theta;
+            let phi = dir.scalar_mul(theta);
+            let (p, r) = (so3::hat(phi), so3::hat(rho));
+            let t2 = theta * theta;
+
+            // reference coefficients, series below 0.1 and closed forms above (both exact to ~1e-11 there)
+            let (a, b, c, ja, jb) = if theta < 0.1 {
+                (1.0 / 6.0 - t2 / 120.0 + t2 * t2 / 5040.0,
+                 1.0 / 24.0 - t2 / 720.0 + t2 * t2 / 40320.0,
+                 1.0 / 120.0 - t2 / 2520.0 + t2 * t2 / 120960.0,
+                 0.5 - t2 / 24.0 + t2 * t2 / 720.0,
+                 1.0 / 6.0 - t2 / 120.0 + t2 * t2 / 5040.0)
+            } else {
+                let (s, co) = (theta.sin(), theta.cos());
+                ((theta - s) / (t2 * theta),
+                 (t2 + 2.0 * co - 2.0) / (2.0 * t2 * t2),
+                 (2.0 * theta - 3.0 * s + theta * co) / (2.0 * t2 * t2 * theta),
+                 (1.0 - co) / t2,
+                 (theta - s) / (t2 * theta))
+            };
+
+            let jl = so3::left_jacobian(phi);
+            assert!(mat3_close(jl, Mat3::identity() + p * ja + p * p * jb, 1.0e-12));
+
+            let q = r * 0.5
+                + (p * r + r * p + p * r * p) * a
+                + (p * p * r + r * p * p - p * r * p * 3.0) * b
+                + (p * r * p * p + p * p * r * p) * c;
+            let block = se3::left_jacobian(Twist::new(rho, phi)).blocks[0][1];
+            assert!(mat3_close(block, q, 1.0e-12));
+
+            let inv = so3::left_jacobian_inv(phi);
+            assert!(mat3_close(inv * jl, Mat3::identity(), 1.0e-12));
+        }
+    }
+
+    #[test]
+    fn quat_swing_twist() {
+        let axis = Vec3::new(0.0, 1.0, 0.0);
+        let twist = Quat::new_from_angle_axis(40.0_f64.radians(), axis);
+        let swing = Quat::new_from_angle_axis(25.0_f64.radians(), Vec3::new(1.0, 0.0, 1.0));
+        let q = swing * twist;
+
+        let (s, t) = q.swing_twist(axis);
+        assert!((s * t - q).length() < 0.000001);
+        assert!(t.dot(&twist).abs() > 0.999999);
+        assert!(s.dot(&swing).abs() > 0.999999);
+        assert!((t.twist_angle(axis) - 40.0_f64.radians()).abs() < 0.000001);
+        // the swing leaves no rotation about the axis
+        assert!(Vec3::from(s).dot(&axis).abs() < 0.000001);
+
+        // works with rot_between_vecs: the swing is what moves the axis
+        let dest = q.rotate_vec3(axis);
+        assert!(Quat::rot_between_vecs(axis, dest).dot(&s).abs() > 0.999999);
+
+        // a half turn perpendicular to the axis has no twist
+        let flip = Quat::new_from_angle_axis(180.0_f64.radians(), Vec3::new(1.0, 0.0, 0.0));
+        let (s, t) = flip.swing_twist(axis);
+        assert_eq!(t, Quat::new(1.0, 0.0, 0.0, 0.0));
+        assert!((s - flip).length() < 0.000001);
+    }
+
+    #[test]
+    fn quat_joint_limits() {
+        let axis = Vec3::new(0.0, 0.0, 1.0);
+        let swing = Quat::new_from_angle_axis(60.0_f64.radians(), Vec3::new(1.0, 1.0, 0.0));
+        let twist = Quat::new_from_angle_axis(-100.0_f64.radians(), axis);
+
+        let clamped_swing = swing.clamp_swing(30.0_f64.radians());
+        let expected = Quat::new_from_angle_axis(30.0_f64.radians(), Vec3::new(1.0, 1.0, 0.0));
+        assert!(clamped_swing.dot(&expected).abs() > 0.999999);
+        assert!(swing.clamp_swing(90.0_f64.radians()).dot(&swing).abs() > 0.999999);
+
+        let clamped_twist = twist.clamp_twist(axis, -45.0_f64.radians(), 45.0_f64.radians());
+        assert!((clamped_twist.twist_angle(axis) + 45.0_f64.radians()).abs() < 0.000001);
+
+        let limited = (swing * twist).clamp_swing_twist(axis, 30.0_f64.radians(), -45.0_f64.radians(), 45.0_f64.radians());
+        let (s, t) = limited.swing_twist(axis);
+        assert!(s.dot(&expected).abs() > 0.999999);
+        assert!((t.twist_angle(axis) + 45.0_f64.radians()).abs() < 0.000001);
+
+        // within limits nothing changes
+        let q = Quat::new_from_angle_axis(10.0_f64.radians(), Vec3::new(1.0, 0.0, 0.0))
+            * Quat::new_from_angle_axis(20.0_f64.radians(), axis);
+        let same = q.clamp_swing_twist(axis, 30.0_f64.radians(), -45.0_f64.radians(), 45.0_f64.radians());
+        assert!(same.dot(&q).abs() > 0.999999);
+    }
+
+    #[test]
+    fn quat_average() {
+        let axis = Vec3::new(0.0, 0.0, 1.0);
+        let a = Quat::new_from_angle_axis(10.0_f64.radians(), axis);
+        let b = Quat::new_from_angle_axis(30.0_f64.radians(), axis);
+        let expected = Quat::new_from_angle_axis(20.0_f64.radians(), axis);
+
+        // b from the opposite hemisphere, a plain sum would cancel out
+        let qs = [a, -b];
+        let mean = Quat::average(&qs, &[1.0, 1.0]).unwrap();
+        assert!(mean.dot(&expected) > 0.999999);
+        assert!(mean.dot(&a) > 0.0);
+
+        let chordal = Quat::chordal_mean(&qs, &[1.0, 1.0]).unwrap();
+        assert!(chordal.dot(&mean) > 0.999999);
+        let geodesic = Quat::geodesic_mean(&qs, &[1.0, 1.0]).unwrap();
+        assert!(geodesic.dot(&mean).abs() > 0.999999);
+
+        // weights pull towards the heavier rotation; about one axis the geodesic mean is exact
+        let weighted = Quat::geodesic_mean(&qs, &[1.0, 3.0]).unwrap();
+        let expected = Quat::new_from_angle_axis(25.0_f64.radians(), axis);
+        assert!(weighted.dot(&expected).abs() > 0.999999);
+
+        assert!((a.angular_distance(&-b) - 20.0_f64.radians()).abs() < 0.000001);
+        assert!((mean.dispersion(&qs, &[1.0, 1.0]) - 10.0_f64.radians()).abs() < 0.000001);
+
+        assert!(Quat::average(&[], &[]).is_none());
+        assert!(Quat::average(&qs, &[0.0, 0.0]).is_none());
+
+        // a scattered set: the chordal and markley means agree, the geodesic one is close
+        let qs: Vec<Quat> = (0..6)
+            .map(|i| {
+                let i = i as f64;
+                Quat::new_from_euler((0.3 + 0.1 * i, -0.2 + 0.05 * i * i, 0.1 * (i - 3.0)), EulerOrder::XYZ, EulerFrame::Intrinsic)
+            })
+            .collect();
+        let weights = [1.0, 2.0, 1.0, 0.5, 1.0, 1.5];
+        let markley = Quat::average(&qs, &weights).unwrap();
+        let chordal = Quat::chordal_mean(&qs, &weights).unwrap();
+        let geodesic = Quat::geodesic_mean(&qs, &weights).unwrap();
+        assert!(markley.angular_distance(&chordal) < 0.000001);
+        assert!(markley.angular_distance(&geodesic) < 0.05);
+        assert!(geodesic.dispersion(&qs, &weights) <= markley.dispersion(&qs, &weights) + 0.000001);
+    }
+
+    #[test]
+    fn quat_chordal_mean_negative_determinant() {
+        let pi = ::std::f64::consts::PI;
+        let rx = Quat::new_from_angle_axis(pi, Vec3::unit_x());
+        let ry = Quat::new_from_angle_axis(pi, Vec3::new(0.0, 1.0, 0.0));
+        let rz = Quat::new_from_angle_axis(pi, Vec3::unit_z());
+        let qs = [rx, ry, rz];
+
+        // the matrix mean is diag(-1.5, -1.5, -0.5) / 3.5, the nearest rotation is rz(pi)
+        let mean = Quat::chordal_mean(&qs, &[1.0, 1.0, 1.5]).unwrap();
+        assert!((mean.length() - 1.0).abs() < 0.000001);
+        assert!(Mat3::from(mean).is_rotation(0.000001));
+        assert!(mean.angular_distance(&rz) < 0.000001);
+
+        // equal weights sum to -I, every half turn is a minimizer
+        let mean = Quat::chordal_mean(&qs, &[1.0, 1.0, 1.0]).unwrap();
+        assert!((mean.length() - 1.0).abs() < 0.000001);
+        assert!(Mat3::from(mean).is_rotation(0.000001));
+        assert!(mean.w().abs() < 0.000001);
+    }
+
+    #[test]
+    fn sampling_rotations() {
+        let mut rng = Rng::new(7);
+        let mut again = Rng::new(7);
+        assert_eq!(rng.next_u64(), again.next_u64());
+
+        // monte-carlo check that quaternion and matrix rotation agree
+        let mut sum = Vec3::zero();
+        for _ in 0..2000 {
+            let q: Quat = sampling::uniform_quat(&mut rng);
+            assert!((q.length() - 1.0).abs() < 0.000001);
+            let v: Vec3 = sampling::uniform_sphere(&mut rng);
+            assert!((v.length() - 1.0).abs() < 0.000001);
+            assert!((q.rotate_vec3(v) - v.apply_rot_mat3(Mat3::from(q))).length() < 0.000001);
+            sum += q.rotate_vec3(Vec3::unit_z());
+        }
+        // uniform rotations scatter a fixed vector evenly over the sphere
+        assert!(sum.scalar_div(2000.0).length() < 0.05);
+
+        let f: Quatf = sampling::uniform_quat(&mut Rng::new(1));
+        assert!((f.length() - 1.0).abs() < 0.0001);
+    }
+
+    #[test]
+    fn sampling_spheres() {
+        let mut rng = Rng::new(42);
+        let n = Vec3::new(1.0, -2.0, 0.5).unitize();
+        let count = 4000;
+        let mut uniform = Vec3::zero();
+        let mut cosine = 0.0;
+        for _ in 0..count {
+            uniform += sampling::uniform_sphere(&mut rng);
+            let h = sampling::uniform_hemisphere(&mut rng, n);
+            assert!(h.dot(&n) >= 0.0);
+            let c = sampling::cosine_hemisphere(&mut rng, n);
+            assert!((c.length() - 1.0).abs() < 0.000001);
+            assert!(c.dot(&n) >= 0.0);
+            cosine += c.dot(&n);
+        }
+        assert!(uniform.scalar_div(count as f64).length() < 0.05);
+        // E[cos theta] is 2/3 under the cosine weighted density
+        assert!((cosine / count as f64 - 2.0 / 3.0).abs() < 0.02);
+
+        let points: Vec<Vec3> = sampling::fibonacci_sphere(500);
+        assert_eq!(points.len(), 500);
+        assert!(points.iter().all(|p| (p.length() - 1.0).abs() < 0.000001));
+        let mean: Vec3 = points.iter().sum();
+        assert!(mean.scalar_div(500.0).length() < 0.01);
+    }
+
+    #[test]
+    fn point_set_alignment() {
+        let mut rng = Rng::new(3);
+        let source: Vec<Vec3> = (0..20).map(|_| sampling::uniform_sphere::<f64>(&mut rng).scalar_mul(2.0)).collect();
+        let q = Quat::new_from_angle_axis(70.0_f64.radians(), Vec3::new(1.0, 2.0, -1.0));
+        let t = Vec3::new(0.5, -3.0, 10.0);
+
+        let target: Vec<Vec3> = source.iter().map(|p| q.rotate_vec3(*p) + t).collect();
+        let a = alignment::align(&source, &target, false).unwrap();
+        assert!(a.rotation.dot(&q).abs() > 0.999999);
+        assert!(mat3_close(a.matrix, Mat3::from(q), 0.000001));
+        assert!((a.translation - t).length() < 0.000001);
+        assert_eq!(a.scale, 1.0);
+        assert!(a.rmsd < 0.000001);
+        assert!(!a.reflection);
+
+        // umeyama scale
+        let scaled: Vec<Vec3> = source.iter().map(|p| q.rotate_vec3(*p).scalar_mul(2.5) + t).collect();
+        let a = alignment::align(&source, &scaled, true).unwrap();
+        assert!((a.scale - 2.5).abs() < 0.000001);
+        assert!((a.transform_point(source[3]) - scaled[3]).length() < 0.000001);
+
+        // a mirrored target is detected and still fit with a proper rotation
+        let mirrored: Vec<Vec3> = target.iter().map(|p| Vec3::new(-p.x(), p.y(), p.z())).collect();
+        let a = alignment::align(&source, &mirrored, false).unwrap();
+        assert!(a.reflection);
+        assert!(a.matrix.is_rotation(0.000001));
+        assert!(a.rmsd > 0.1);
+
+        // 180 degree turn, where the covariance is symmetric
+        let half = Quat::new_from_angle_axis(180.0_f64.radians(), Vec3::new(0.0, 1.0, 0.0));
+        let turned: Vec<Vec3> = source.iter().map(|p| half.rotate_vec3(*p)).collect();
+        let a = alignment::align(&source, &turned, false).unwrap();
+        assert!(a.rotation.dot(&half).abs() > 0.999999);
+
+        assert!(alignment::align::<f64>(&[], &[], false).is_none());
+        assert!(alignment::align(&source, &target[1..], false).is_none());
+    }
+
+    #[test]
+    fn icp_registration() {
+        // an ellipsoid has no rotational symmetry, so the registration is unique
+        let radii = Vec3::new(3.0, 2.0, 1.0);
+        let unit: Vec<Vec3> = sampling::fibonacci_sphere(400);
+        let target: Vec<Vec3> = unit.iter().map(|p| Vec3::new(p.x() * radii.x(), p.y() * radii.y(), p.z() * radii.z())).collect();
+        let normals: Vec<Vec3> = unit.iter().map(|p| Vec3::new(p.x() / radii.x(), p.y() / radii.y(), p.z() / radii.z()).unitize()).collect();
+
+        let q = Quat::new_from_angle_axis(8.0_f64.radians(), Vec3::new(1.0, 1.0, 0.0));
+        let t = Vec3::new(0.1, -0.05, 0.08);
+        let truth = DualQuat::new(q, t);
+        let inverse = truth.inverse();
+        let mut source: Vec<Vec3> = target.iter().map(|p| inverse.transform_point(*p)).collect();
+
+        let options = IcpOptions::default();
+        let r = icp::point_to_point(&source, &target, DualQuat::identity(), options).unwrap();
+        assert!(r.converged);
+        assert!(r.rotation.dot(&q).abs() > 0.999999);
+        assert!((r.translation - t).length() < 0.000001);
+        assert!(r.rmse < 0.000001);
+        assert_eq!(r.inliers, 400);
+
+        let r = icp::point_to_plane(&source, &target, &normals, DualQuat::identity(), options).unwrap();
+        assert!(r.converged);
+        assert!(r.rotation.dot(&q).abs() > 0.999999);
+        assert!((r.transform_point(source[10]) - target[10]).length() < 0.000001);
+        assert!(r.iterations < options.max_iterations);
+
+        // outliers are rejected by distance, starting from a nearby initial guess
+        source.push(Vec3::new(20.0, 0.0, 0.0));
+        source.push(Vec3::new(0.0, -15.0, 4.0));
+        let options = IcpOptions { max_correspondence_distance: 1.0, ..IcpOptions::default() };
+        let initial = DualQuat::new(Quat::new_from_angle_axis(5.0_f64.radians(), Vec3::new(1.0, 1.0, 0.0)), Vec3::zero());
+        let r = icp::point_to_point(&source, &target, initial, options).unwrap();
+        assert_eq!(r.inliers, 400);
+        assert!(r.rotation.dot(&q).abs() > 0.999999);
+        assert!(mat4_close(r.matrix(), Mat4::from(truth), 0.000001));
+
+        // losing correspondences after the first step keeps the transform found so far
+        let target = [Vec3::new(0.0, 2.0, 1.0), Vec3::new(2.0, 2.0, 2.0), Vec3::new(0.0, -1.0, 1.0)];
+        let source = [Vec3::new(2.0, 1.0, 2.0), Vec3::new(0.0, -2.0, 1.0), Vec3::new(0.0, 0.0, 1.0)];
+        let options = IcpOptions { max_correspondence_distance: 1.0, ..IcpOptions::default() };
+        let r = icp::point_to_point(&source, &target, DualQuat::identity(), options).unwrap();
+        assert_eq!(r.iterations, 1);
+        assert!(!r.converged);
+        assert_eq!(r.inliers, 3);
+
+        // a single plane cannot constrain point-to-plane
+        let flat: Vec<Vec3> = (0..25).map(|i| Vec3::new((i % 5) as f64, (i / 5) as f64, 0.0)).collect();
+        let up = vec![Vec3::unit_z(); 25];
+        assert!(icp::point_to_plane(&flat, &flat, &up, DualQuat::identity(), IcpOptions::default()).is_none());
+    }
+
+    #[test]
+    fn icp_scan_sized_cloud() {
+        // tens of thousands of target points, a brute force matcher would take minutes here
+        let radii = Vec3::new(3.0, 2.0, 1.0);
+        let target: Vec<Vec3> = sampling::fibonacci_sphere::<f64>(40000).iter()
+            .map(|p| Vec3::new(p.x() * radii.x(), p.y() * radii.y(), p.z() * radii.z()))
+            .collect();
+        let q = Quat::new_from_angle_axis(6.0_f64.radians(), Vec3::new(0.0, 1.0, 1.0));
+        let t = Vec3::new(-0.05, 0.1, 0.02);
+        let inverse = DualQuat::new(q, t).inverse();
+        let source: Vec<Vec3> = target.iter().step_by(8).map(|p| inverse.transform_point(*p)).collect();
+
+        // a dense target makes point-to-point creep along the surface, it needs more steps
+        let options = IcpOptions { max_iterations: 200, ..IcpOptions::default() };
+        let r = icp::point_to_point(&source, &target, DualQuat::identity(), options).unwrap();
+        assert!(r.converged);
+        assert_eq!(r.inliers, 5000);
+        assert!(r.rotation.dot(&q).abs() > 0.999999);
+        assert!((r.translation - t).length() < 0.000001);
+    }
+
+    #[test]
+    fn mat3_decompositions() {
+        let m = Mat3 { v: [2.0, -1.0, 3.0, 4.0, 0.5, -2.0, 1.0, 1.0, 1.0] };
+
+        let (p, l, u) = m.lu();
+        assert!(mat3_close(p * m, l * u, 0.000001));
+        assert!(l.v[1] == 0.0 && l.v[2] == 0.0 && l.v[5] == 0.0);
+        assert!(u.v[3] == 0.0 && u.v[6] == 0.0 && u.v[7] == 0.0);
+        let b = Vec3::new(1.0, 2.0, 3.0);
+        let x = m.solve(b).unwrap();
+        assert!((m * x - b).length() < 0.000001);
+        let singular = Mat3 { v: [1.0, 2.0, 3.0, 2.0, 4.0, 6.0, 0.0, 1.0, 1.0] };
+        assert!(singular.solve(b).is_none());
+
+        let (q, r) = m.qr();
+        assert!(mat3_close(q * r, m, 0.000001));
+        assert!(mat3_close(q.transpose() * q, Mat3::identity(), 0.000001));
+        assert!(r.v[3] == 0.0 && r.v[6] == 0.0 && r.v[7] == 0.0);
+
+        let spd = m.transpose() * m + Mat3::identity();
+        let c = spd.cholesky().unwrap();
+        assert!(mat3_close(c * c.transpose(), spd, 0.000001));
+        assert!(m.cholesky().is_none());
+
+        let (values, vectors) = spd.symmetric_eigen();
+        assert!(values[0] >= values[1] && values[1] >= values[2]);
+        for (i, value) in values.iter().enumerate() {
+            assert!((spd * vectors.col(i) - vectors.col(i).scalar_mul(*value)).length() < 0.000001);
+        }
+        assert!((values.iter().sum::<f64>() - spd.trace()).abs() < 0.000001);
+
+        let (u, s, v) = m.svd();
+        let d = Mat3 { v: [s[0], 0.0, 0.0, 0.0, s[1], 0.0, 0.0, 0.0, s[2]] };
+        assert!(mat3_close(u * d * v.transpose(), m, 0.000001));
+        assert!(mat3_close(u.transpose() * u, Mat3::identity(), 0.000001));
+        assert!((s[0] * s[1] * s[2] - m.determinant().abs()).abs() < 0.000001);
+
+        // rank one: the missing columns of u are completed orthonormally
+        let a = Vec3::new(1.0, 2.0, 2.0);
+        let b = Vec3::new(0.0, 3.0, 4.0);
+        let outer = Mat3::new_from_cols(a.scalar_mul(b.x()), a.scalar_mul(b.y()), a.scalar_mul(b.z()));
+        let (u, s, v) = outer.svd();
+        assert!((s[0] - 15.0).abs() < 0.000001 && s[1].abs() < 0.00001);
+        let d = Mat3 { v: [s[0], 0.0, 0.0, 0.0, s[1], 0.0, 0.0, 0.0, s[2]] };
+        assert!(mat3_close(u * d * v.transpose(), outer, 0.00001));
+        assert!(mat3_close(u.transpose() * u, Mat3::identity(), 0.000001));
+    }
+
+    #[test]
+    fn mat3_svd_small_singular_values() {
+        // squaring the matrix would push 1e-10 below the round-off of the largest value
+        let r1 = Mat3::new_from_angle_axis(0.7, Vec3::new(1.0, -2.0, 0.5));
+        let r2 = Mat3::new_from_angle_axis(-1.2, Vec3::new(0.3, 1.0, 2.0));
+        let d = Mat3 { v: [1.0, 0.0, 0.0, 0.0, 1.0e-5, 0.0, 0.0, 0.0, 1.0e-10] };
+        let m = r1 * d * r2.transpose();
+
+        let (u, s, v) = m.svd();
+        assert!((s[0] - 1.0).abs() < 1.0e-12);
+        assert!((s[1] - 1.0e-5).abs() < 1.0e-5 * 1.0e-8);
+        assert!((s[2] - 1.0e-10).abs() < 1.0e-10 * 1.0e-4);
+        assert!(mat3_close(u.transpose() * u, Mat3::identity(), 1.0e-12));
+        assert!(mat3_close(v.transpose() * v, Mat3::identity(), 1.0e-12));
+        let d = Mat3 { v: [s[0], 0.0, 0.0, 0.0, s[1], 0.0, 0.0, 0.0, s[2]] };
+        assert!(mat3_close(u * d * v.transpose(), m, 1.0e-14));
+        // the smallest direction is recovered too
+        assert!(v.col(2).dot(&r2.col(2)).abs() > 1.0 - 1.0e-6);
+    }
+
+    #[test]
+    fn mat4_decompositions() {
+        let m = Mat4 { v: [
+            0.0, 2.0, -1.0, 3.0,
+            1.0, 1.0, 0.0, -2.0,
+            4.0, 0.5, 2.0, 1.0,
+            -1.0, 3.0, 1.0, 0.0 ] };
+
+        let (p, l, u) = m.lu();
+        assert!(mat4_close(p * m, l * u, 0.000001));
+        let b = Vec4::new(1.0, -1.0, 2.0, 0.5);
+        let x = m.solve(b).unwrap();
+        assert!((m * x - b).length() < 0.000001);
+
+        let (q, r) = m.qr();
+        assert!(mat4_close(q * r, m, 0.000001));
+        assert!(mat4_close(q.transpose() * q, Mat4::identity(), 0.000001));
+
+        let spd = m.transpose() * m;
+        let c = spd.cholesky().unwrap();
+        assert!(mat4_close(c * c.transpose(), spd, 0.000001));
+
+        let (values, vectors) = spd.symmetric_eigen();
+        assert!(mat4_close(vectors.transpose() * vectors, Mat4::identity(), 0.000001));
+        let mut d = Mat4::zero();
+        for (i, value) in values.iter().enumerate() {
+            d.v[i * 5] = *value;
+        }
+        assert!(mat4_close(vectors * d * vectors.transpose(), spd, 0.000001));
+    }
+
+    #[test]
+    fn mat3_polar() {
+        let r = Mat3::new_from_angle_axis(50.0_f64.radians(), Vec3::new(1.0, -1.0, 2.0));
+        let stretch = Mat3 { v: [2.0, 0.3, 0.1, 0.3, 1.5, -0.2, 0.1, -0.2, 0.8] };
+        let p = (r * stretch).polar();
+        assert!(!p.reflection);
+        assert!(mat3_close(p.rotation, r, 0.000001));
+        assert!(mat3_close(p.stretch, stretch, 0.000001));
+        assert!(mat3_close(p.stretch, p.stretch.transpose(), 0.000001));
+
+        let mirror = Mat3 { v: [-1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0] };
+        let m = r * mirror * stretch;
+        let p = m.polar();
+        assert!(p.reflection);
+        assert!(p.rotation.is_rotation(0.000001));
+        assert!(mat3_close(p.rotation * p.stretch, m, 0.000001));
+    }
+
+    #[test]
+    fn mat4_trs() {
+        let q = Quat::new_from_angle_axis(35.0_f64.radians(), Vec3::new(0.0, 1.0, 1.0));
+        let trs = Trs {
+            translation: Vec3::new(1.0, -2.0, 5.0),
+            rotation: q,
+            scale: Vec3::new(2.0, 0.5, 3.0),
+            shear: Vec3::new(0.2, 0.0, -0.4),
+            reflection: false,
+        };
+        let m = trs.matrix();
+        let d = m.decompose_trs().unwrap();
+        assert!((d.translation - trs.translation).length() < 0.000001);
+        assert!(d.rotation.dot(&q).abs() > 0.999999);
+        assert!((d.scale - trs.scale).length() < 0.000001);
+        assert!((d.shear - trs.shear).length() < 0.000001);
+        assert!(!d.reflection);
+
+        // plain scale and rotation match Transform
+        let t = Transform::new_from_quat(q, Vec3::new(0.0, 1.0, 0.0), Vec3::new(1.0, 2.0, 3.0));
+        let d = t.matrix().decompose_trs().unwrap();
+        assert!(d.shear.length() < 0.000001);
+        assert!((d.scale - Vec3::new(1.0, 2.0, 3.0)).length() < 0.000001);
+
+        // a mirror shows up as a negative scale and still recomposes
+        let mirrored = m * Mat4::new_scale(Vec3::new(1.0, 1.0, -1.0));
+        let d = mirrored.decompose_trs().unwrap();
+        assert!(d.reflection);
+        assert!(d.scale.x() < 0.0);
+        assert!(Mat3::from(d.rotation).is_rotation(0.000001));
+        assert!(mat4_close(d.matrix(), mirrored, 0.000001));
+
+        assert!(Mat4::new_scale(Vec3::new(1.0, 0.0, 1.0)).decompose_trs().is_none());
+        let projective = Mat4::new_perspective(1.0, 1.0, 0.1, 10.0, Handedness::Right, DepthRange::NegOneToOne);
+        assert!(projective.decompose_trs().is_none());
+    }
+
+    #[test]
+    fn generic_vectors_and_matrices() {
+        let a = VecN::new([1.0, 2.0, 3.0, 4.0, 5.0]);
+        let b = VecN::new([5.0, 4.0, 3.0, 2.0, 1.0]);
+        assert_eq!(a + b, VecN::new([6.0; 5]));
+        assert_eq!(a.dot(&b), 35.0);
+        assert!((a.unitize().length() - 1.0).abs() < 0.000001);
+        let v2 = Vec2::new([3.0, 4.0]);
+        assert_eq!(v2.length(), 5.0);
+
+        let m: Mat<2, 3> = Mat::new([[1.0, 2.0, 3.0], [4.0, 5.0, 6.0]]);
+        let mt = m.transpose();
+        assert_eq!(mt.v, [[1.0, 4.0], [2.0, 5.0], [3.0, 6.0]]);
+        let p = m * mt;
+        assert_eq!(p.v, [[14.0, 32.0], [32.0, 77.0]]);
+        assert_eq!(m * VecN::new([1.0, 0.0, -1.0]), Vec2::new([-2.0, -2.0]));
+        assert_eq!(p.determinant(), 14.0 * 77.0 - 32.0 * 32.0);
+        assert!(Mat::<3, 3>::new([[1.0, 2.0, 3.0], [2.0, 4.0, 6.0], [0.0, 1.0, 1.0]]).solve(VecN::zero()).is_none());
+
+        // the existing types convert losslessly and agree with the generic products
+        let r = Mat3::new_from_angle_axis(0.7, Vec3::new(1.0, 2.0, 3.0));
+        let s = Mat3 { v: [2.0, -1.0, 3.0, 4.0, 0.5, -2.0, 1.0, 1.0, 1.0] };
+        assert!(mat3_close(Mat3::from(Mat::from(r) * Mat::from(s)), r * s, 0.000001));
+        assert_eq!(Vec3::from(VecN::from(Vec3::new(1.0, 2.0, 3.0))), Vec3::new(1.0, 2.0, 3.0));
+        let m4 = Mat4::new_translation(Vec3::new(1.0, 2.0, 3.0));
+        let inv = Mat::from(m4).try_inverse().unwrap();
+        assert!(mat4_close(Mat4::from(inv), m4.try_inverse().unwrap(), 0.000001));
+        let f: Matf<2, 2> = Matf::new([[1.0, 2.0], [3.0, 4.0]]);
+        assert_eq!(Mat::<2, 2>::from(f).v, [[1.0, 2.0], [3.0, 4.0]]);
+
+        // 6x6 solves and twists
+        let xi = Twist::new(Vec3::new(0.1, -0.2, 0.3), Vec3::new(0.5, 0.0, -0.4));
+        let adjoint = se3::adjoint(se3::exp(Twist::new(Vec3::new(1.0, 2.0, 3.0), Vec3::new(0.3, 0.2, 0.1))));
+        let ad: Mat6 = Mat::from(adjoint);
+        let moved = Twist::from(ad * Vec6::from(xi));
+        assert!((moved - adjoint * xi).rho.length() < 0.000001);
+        let back = Twist::from(ad.solve(Vec6::from(moved)).unwrap());
+        assert!((back - xi).rho.length() < 0.000001 && (back - xi).phi.length() < 0.000001);
+    }
+
+    #[test]
+    fn generic_lu() {
+        let mut rng = Rng::new(20);
+        let mut m: Mat6 = Mat::zero();
+        for row in m.v.iter_mut() {
+            for e in row.iter_mut() {
+                *e = rng.next_f64() - 0.5;
+            }
+        }
+        let (p, l, u) = m.lu();
+        let (pl, lu) = (p * m, l * u);
+        for (a, b) in pl.v.iter().flat_map(|r| r.iter()).zip(lu.v.iter().flat_map(|r| r.iter())) {
+            assert!((a - b).abs() < 1.0e-12);
+        }
+        for r in 0..6 {
+            assert_eq!(l[(r, r)], 1.0);
+            assert!((0..r).all(|c| u[(r, c)] == 0.0));
+        }
+
+        // Mat3 and Mat4 factor through the same code
+        let s = Mat3 { v: [2.0, -1.0, 3.0, 4.0, 0.5, -2.0, 1.0, 1.0, 1.0] };
+        let (p3, l3, u3) = s.lu();
+        let (gp, gl, gu) = Mat::from(s).lu();
+        assert_eq!((p3.v, l3.v, u3.v), (Mat3::from(gp).v, Mat3::from(gl).v, Mat3::from(gu).v));
+
+        // a singular matrix still factors, with a zero pivot left in u
+        let singular = Mat3 { v: [1.0, 2.0, 3.0, 2.0, 4.0, 6.0, 0.0, 1.0, 1.0] };
+        let (p, l, u) = singular.lu();
+        assert!(mat3_close(p * singular, l * u, 1.0e-12));
+        assert!(u.m22().abs() < 1.0e-12);
+        assert!(singular.solve(Vec3::new(1.0, 0.0, 0.0)).is_none());
+    }
+
+    #[test]
+    fn spatial_inertia() {
+        let mass = 2.0;
+        let com = Vec3::new(0.3, -0.1, 0.2);
+        let inertia = Mat3 { v: [0.4, 0.01, 0.0, 0.01, 0.3, 0.02, 0.0, 0.02, 0.5] };
+        let m = Mat6::new_spatial_inertia(mass, com, inertia);
+        assert_eq!(m, m.transpose());
+
+        // kinetic energy matches the centre of mass form
+        let v = Vec3::new(1.0, 0.5, -0.3);
+        let w = Vec3::new(0.2, -0.4, 0.9);
+        let xi = Vec6::from(Twist::new(v, w));
+        let energy = 0.5 * xi.dot(&(m * xi));
+        let v_com = v + w.cross(&com);
+        let expected = 0.5 * mass * v_com.dot(&v_com) + 0.5 * w.dot(&(inertia * w));
+        assert!((energy - expected).abs() < 0.000001);
+    }
+
+    #[test]
+    fn geometry_2d() {
+        let a = Vec2::new([1.0, 0.0]);
+        let b = Vec2::new([0.0, 2.0]);
+        assert_eq!(a.cross(&b), 2.0);
+        assert_eq!(a.perp(), Vec2::new([0.0, 1.0]));
+        assert!((a.angle(&b) - 90.0_f64.radians()).abs() < 0.000001);
+        assert!((b.angle(&a) + 90.0_f64.radians()).abs() < 0.000001);
+
+        let r = Rot2::new(30.0_f64.radians());
+        let m = Mat2::new_rotation(30.0_f64.radians());
+        let v = Vec2::new([2.0, -1.0]);
+        assert!((r * v - m * v).length() < 0.000001);
+        assert!(((r * r).angle() - 60.0_f64.radians()).abs() < 0.000001);
+        assert!(((r * r.inverse()).angle()).abs() < 0.000001);
+        assert!((Rot2::from(m).angle() - r.angle()).abs() < 0.000001);
+        assert!((Rot2::rot_between_vecs(a, b).angle() - 90.0_f64.radians()).abs() < 0.000001);
+        // the short way round across pi
+        let from = Rot2::new(170.0_f64.radians());
+        let to = Rot2::new(-170.0_f64.radians());
+        assert!((from.slerp(to, 0.5).angle().abs() - 180.0_f64.radians()).abs() < 0.000001);
+
+        let t = Transform2::new(r, Vec2::new([1.0, 2.0]), Vec2::new([2.0, 3.0]));
+        let p = Vec2::new([1.0, 1.0]);
+        let expected = r * Vec2::new([2.0, 3.0]) + Vec2::new([1.0, 2.0]);
+        assert!((t.transform_point(p) - expected).length() < 0.000001);
+        assert!((t.transform_vector(p) - r * Vec2::new([2.0, 3.0])).length() < 0.000001);
+        let inv = t.try_inverse().unwrap();
+        assert!((inv.transform_point(t.transform_point(p)) - p).length() < 0.000001);
+        assert!(((t * inv).matrix() - Affine2::identity().matrix()).v.iter().flat_map(|row| row.iter()).all(|e| e.abs() < 0.000001));
+        assert!(Affine2::try_from_mat3(Mat::new([[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 1.0, 1.0]])).is_none());
+        assert!(Transform2::new_scale(Vec2::new([0.0, 1.0])).try_inverse().is_none());
+    }
+
+    #[test]
+    fn plane_projection() {
+        let origin = Vec3::new(1.0, 2.0, 3.0);
+        let normal = Vec3::new(1.0, 1.0, 1.0);
+        let plane = PlaneBasis::new(origin, normal);
+        assert!((plane.normal() - normal.unitize()).length() < 0.000001);
+        assert!(plane.u.dot(&plane.v).abs() < 0.000001);
+
+        // lifting and projecting round-trip, out of plane offsets are dropped
+        let q = Vec2::new([0.5, -2.0]);
+        let p = plane.lift(q);
+        assert!((plane.project(p) - q).length() < 0.000001);
+        assert!((plane.project(p + normal.scalar_mul(4.0)) - q).length() < 0.000001);
+
+        // a floor plan on the xy plane with a chosen basis
+        let floor = PlaneBasis::new_from_axes(Vec3::zero(), Vec3::new(2.0, 0.0, 0.0), Vec3::new(1.0, 1.0, 0.0));
+        assert_eq!(floor.project(Vec3::new(3.0, 4.0, 5.0)), Vec2::new([3.0, 4.0]));
+        let rotated = Rot2::new(90.0_f64.radians()) * floor.project(Vec3::new(1.0, 0.0, 0.0));
+        assert!((floor.lift(rotated) - Vec3::new(0.0, 1.0, 0.0)).length() < 0.000001);
+    }
+
+    #[test]
+    fn plane_queries() {
+        let plane = Plane::new_from_three_points(
+            Vec3::new(0.0, 0.0, 2.0), Vec3::new(1.0, 0.0, 2.0), Vec3::new(0.0, 1.0, 2.0)).unwrap();
+        assert_eq!(plane.normal, Vec3::unit_z());
+        assert_eq!(plane.d, 2.0);
+        assert_eq!(plane.signed_distance(Vec3::new(5.0, 5.0, 3.5)), 1.5);
+        assert_eq!(plane.signed_distance(Vec3::new(5.0, 5.0, 0.0)), -2.0);
+        assert_eq!(plane.project_point(Vec3::new(1.0, 2.0, 7.0)), Vec3::new(1.0, 2.0, 2.0));
+        assert!(Plane::new_from_three_points(Vec3::zero(), Vec3::unit_x(), Vec3::unit_x().scalar_mul(2.0)).is_none());
+
+        let ray = Ray::new(Vec3::new(1.0, 1.0, 0.0), Vec3::new(0.0, 1.0, 1.0));
+        let hit = ray.intersect_plane(&plane).unwrap();
+        assert_eq!(hit.t, 2.0);
+        assert_eq!(hit.point, Vec3::new(1.0, 3.0, 2.0));
+        assert_eq!(hit.normal, Vec3::unit_z());
+        assert!(Ray::new(Vec3::zero(), Vec3::new(0.0, 0.0, -1.0)).intersect_plane(&plane).is_none());
+        assert!(Ray::new(Vec3::zero(), Vec3::unit_x()).intersect_plane(&plane).is_none());
+
+        let x = Plane::new_from_point_normal(Vec3::new(1.0, 0.0, 0.0), Vec3::unit_x()).unwrap();
+        let y = Plane::new(Vec3::new(0.0, 2.0, 0.0), 4.0).unwrap();
+        let p = Plane::intersect_three(&x, &y, &plane).unwrap();
+        assert!((p.point - Vec3::new(1.0, 2.0, 2.0)).length() < 0.000001);
+        assert!((p.determinant - 1.0).abs() < 0.000001);
+        let tilted = Plane::new(Vec3::new(1.0, 1.0, 0.0), 1.0).unwrap();
+        assert!(Plane::intersect_three(&x, &plane, &Plane::new(Vec3::unit_z(), 5.0).unwrap()).is_none());
+        let q = Plane::intersect_three(&x, &tilted, &plane).unwrap();
+        assert!(x.signed_distance(q.point).abs() < 0.000001 && tilted.signed_distance(q.point).abs() < 0.000001);
+        assert!((q.determinant.abs() - 0.5_f64.sqrt()).abs() < 0.000001);
+
+        // a zero normal gives no plane instead of NaNs
+        assert!(Plane::new(Vec3::zero(), 1.0).is_none());
+        assert!(Plane::new_from_point_normal(Vec3::unit_x(), Vec3::zero()).is_none());
+    }
+
+    #[test]
+    fn line_and_segment_distances() {
+        // skew lines, x axis and a line parallel to y at height 1
+        let a = Line::new(Vec3::zero(), Vec3::unit_x());
+        let b = Line::new_through(Vec3::new(3.0, -1.0, 1.0), Vec3::new(3.0, 1.0, 1.0));
+        let c = a.closest_points(&b);
+        assert!((c.point_a - Vec3::new(3.0, 0.0, 0.0)).length() < 0.000001);
+        assert!((c.point_b - Vec3::new(3.0, 0.0, 1.0)).length() < 0.000001);
+        assert!((c.s - 3.0).abs() < 0.000001 && (c.t - 0.5).abs() < 0.000001);
+        assert!((c.distance - 1.0).abs() < 0.000001);
+
+        let parallel = Line::new(Vec3::new(0.0, 2.0, 0.0), Vec3::new(-2.0, 0.0, 0.0));
+        assert!((a.closest_points(&parallel).distance - 2.0).abs() < 0.000001);
+        assert_eq!(a.closest_point(Vec3::new(4.0, 1.0, 1.0)), (4.0, Vec3::new(4.0, 0.0, 0.0)));
+
+        // crossing segments, closest pair inside both
+        let s1 = Segment::new(Vec3::new(-1.0, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0));
+        let s2 = Segment::new(Vec3::new(0.0, -1.0, 0.5), Vec3::new(0.0, 1.0, 0.5));
+        let c = s1.closest_points(&s2);
+        assert!((c.s - 0.5).abs() < 0.000001 && (c.t - 0.5).abs() < 0.000001);
+        assert!((c.distance - 0.5).abs() < 0.000001);
+
+        // the infinite lines would meet, the segments end before that
+        let s3 = Segment::new(Vec3::new(3.0, 1.0, 0.0), Vec3::new(3.0, 4.0, 0.0));
+        let c = s1.closest_points(&s3);
+        assert_eq!(c.point_a, Vec3::new(1.0, 0.0, 0.0));
+        assert_eq!(c.point_b, Vec3::new(3.0, 1.0, 0.0));
+        assert!((s1.distance(&s3) - 5.0_f64.sqrt()).abs() < 0.000001);
+
+        // collinear overlapping, and a degenerate segment
+        let s4 = Segment::new(Vec3::new(0.5, 0.0, 0.0), Vec3::new(2.0, 0.0, 0.0));
+        assert!(s1.distance(&s4) < 0.000001);
+        let point = Segment::new(Vec3::new(0.0, 2.0, 0.0), Vec3::new(0.0, 2.0, 0.0));
+        assert!((s1.distance(&point) - 2.0).abs() < 0.000001);
+        assert_eq!(s1.closest_point(Vec3::new(5.0, 1.0, 0.0)), (1.0, Vec3::new(1.0, 0.0, 0.0)));
+    }
+
+    #[test]
+    fn triangle_basics() {
+        let t = Triangle::new(Vec3::zero(), Vec3::new(4.0, 0.0, 0.0), Vec3::new(0.0, 3.0, 0.0));
+        assert_eq!(t.area(), 6.0);
+        assert_eq!(t.normal(), Vec3::unit_z());
+        assert_eq!(t.centroid(), Vec3::mean_from_vertex_array(t.vertices()));
+        assert_eq!(Vec3::normal_from_vertex_array(t.into()), t.normal());
+        assert_eq!(t.plane().unwrap().d, 0.0);
+
+        let (wa, wb, wc) = t.barycentric(Vec3::new(1.0, 1.0, 5.0)).unwrap();
+        assert!((wa - 5.0 / 12.0).abs() < 0.000001 && (wb - 0.25).abs() < 0.000001 && (wc - 1.0 / 3.0).abs() < 0.000001);
+        let degenerate = Triangle::new(Vec3::zero(), Vec3::unit_x(), Vec3::unit_x().scalar_mul(2.0));
+        assert!(degenerate.is_degenerate());
+        assert!(degenerate.barycentric(Vec3::zero()).is_none());
+
+        // inside, beyond each vertex region and an edge region
+        assert_eq!(t.closest_point(Vec3::new(1.0, 1.0, 2.0)), Vec3::new(1.0, 1.0, 0.0));
+        assert_eq!(t.closest_point(Vec3::new(-1.0, -1.0, 0.0)), t.a);
+        assert_eq!(t.closest_point(Vec3::new(6.0, -1.0, 0.0)), t.b);
+        assert_eq!(t.closest_point(Vec3::new(-1.0, 5.0, 1.0)), t.c);
+        assert_eq!(t.closest_point(Vec3::new(2.0, -3.0, 0.0)), Vec3::new(2.0, 0.0, 0.0));
+        let on_hypotenuse = t.closest_point(Vec3::new(4.0, 3.0, 0.0));
+        assert!((on_hypotenuse - Vec3::new(2.56, 1.08, 0.0)).length() < 0.000001);
+    }
+
+    #[test]
+    fn triangle_ray_intersection() {
+        let t = Triangle::new(Vec3::zero(), Vec3::new(4.0, 0.0, 0.0), Vec3::new(0.0, 3.0, 0.0));
+        let down = Ray::new(Vec3::new(1.0, 1.0, 2.0), Vec3::new(0.0, 0.0, -1.0));
+        let hit = t.intersect_ray(&down, Culling::Back).unwrap();
+        assert_eq!(hit.t, 2.0);
+        assert_eq!(hit.point, Vec3::new(1.0, 1.0, 0.0));
+        assert_eq!(hit.normal, Vec3::unit_z());
+        assert!(hit.front_face);
+        assert!((hit.u - 0.25).abs() < 0.000001 && (hit.v - 1.0 / 3.0).abs() < 0.000001);
+
+        let up = Ray::new(Vec3::new(1.0, 1.0, -2.0), Vec3::new(0.0, 0.0, 1.0));
+        assert!(t.intersect_ray(&up, Culling::Back).is_none());
+        let back = t.intersect_ray(&up, Culling::Disabled).unwrap();
+        assert!(!back.front_face);
+        assert!(t.intersect_ray(&up, Culling::Front).is_some());
+        assert!(t.intersect_ray(&down, Culling::Front).is_none());
+
+        // misses: outside, behind the origin and parallel
+        assert!(t.intersect_ray(&Ray::new(Vec3::new(3.0, 3.0, 2.0), Vec3::new(0.0, 0.0, -1.0)), Culling::Disabled).is_none());
+        assert!(t.intersect_ray(&Ray::new(Vec3::new(1.0, 1.0, -2.0), Vec3::new(0.0, 0.0, -1.0)), Culling::Disabled).is_none());
+        assert!(t.intersect_ray(&Ray::new(Vec3::new(-1.0, 1.0, 0.0), Vec3::unit_x()), Culling::Disabled).is_none());
+    }
+
+    #[test]
+    fn triangle_overlap() {
+        let t = Triangle::new(Vec3::zero(), Vec3::new(4.0, 0.0, 0.0), Vec3::new(0.0, 4.0, 0.0));
+        // piercing
+        let pierce = Triangle::new(Vec3::new(1.0, 1.0, -1.0), Vec3::new(1.0, 1.0, 1.0), Vec3::new(5.0, 5.0, 0.0));
+        assert!(t.intersects_triangle(&pierce));
+        assert!(pierce.intersects_triangle(&t));
+        // above, and beside the hypotenuse
+        let above = Triangle::new(Vec3::new(0.0, 0.0, 1.0), Vec3::new(4.0, 0.0, 1.0), Vec3::new(0.0, 4.0, 1.0));
+        assert!(!t.intersects_triangle(&above));
+        let beside = Triangle::new(Vec3::new(3.0, 3.0, -1.0), Vec3::new(3.0, 3.0, 1.0), Vec3::new(5.0, 5.0, 0.0));
+        assert!(!t.intersects_triangle(&beside));
+        // coplanar, overlapping and disjoint
+        let shifted = Triangle::new(Vec3::new(1.0, 1.0, 0.0), Vec3::new(5.0, 1.0, 0.0), Vec3::new(1.0, 5.0, 0.0));
+        assert!(t.intersects_triangle(&shifted));
+        let far = Triangle::new(Vec3::new(3.0, 3.0, 0.0), Vec3::new(6.0, 3.0, 0.0), Vec3::new(3.0, 6.0, 0.0));
+        assert!(!t.intersects_triangle(&far));
+        // sharing an edge
+        let neighbour = Triangle::new(Vec3::new(4.0, 0.0, 0.0), Vec3::new(4.0, 4.0, 0.0), Vec3::new(0.0, 4.0, 0.0));
+        assert!(t.intersects_triangle(&neighbour));
+    }
+
+    #[test]
+    fn aabb_queries() {
+        let points = [Vec3::new(1.0, -2.0, 0.0), Vec3::new(-1.0, 3.0, 2.0), Vec3::new(0.5, 0.0, -1.0)];
+        let b = Aabb::from_points(&points).unwrap();
+        assert_eq!(b.min, Vec3::new(-1.0, -2.0, -1.0));
+        assert_eq!(b.max, Vec3::new(1.0, 3.0, 2.0));
+        assert_eq!(b.volume(), 30.0);
+        assert_eq!(b.surface_area(), 2.0 * (10.0 + 15.0 + 6.0));
+        assert!(Aabb::<f64>::from_points(&[]).is_none());
+        assert!(Aabb::<f64>::empty().is_empty());
+        assert_eq!(Aabb::empty().merge(&b), b);
+
+        let other = Aabb::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(5.0, 5.0, 5.0));
+        assert!(b.intersects_aabb(&other));
+        assert!(!b.intersects_aabb(&Aabb::new(Vec3::new(2.0, 0.0, 0.0), Vec3::new(3.0, 1.0, 1.0))));
+        assert!(b.merge(&other).contains_aabb(&b) && b.merge(&other).contains_aabb(&other));
+        assert!(b.contains_point(Vec3::zero()) && !b.contains_point(Vec3::new(0.0, 4.0, 0.0)));
+        assert!(b.intersects_sphere(&Sphere::new(Vec3::new(2.0, 0.0, 0.0), 1.0)));
+        assert!(!b.intersects_sphere(&Sphere::new(Vec3::new(2.0, 4.0, 0.0), 1.0)));
+
+        // rotating by 90 degrees about z swaps the x and y extents
+        let r = Mat3::new_from_angle_axis(90.0_f64.radians(), Vec3::unit_z());
+        let moved = b.transform(r, Vec3::new(10.0, 0.0, 0.0));
+        assert!((moved.min - Vec3::new(7.0, -1.0, -1.0)).length() < 0.000001);
+        assert!((moved.max - Vec3::new(12.0, 1.0, 2.0)).length() < 0.000001);
+
+        // slab test from outside and from inside
+        let ray = Ray::new(Vec3::new(-5.0, 0.0, 0.0), Vec3::unit_x());
+        let hit = b.intersect_ray(&ray).unwrap();
+        assert_eq!(hit.t, 4.0);
+        assert_eq!(hit.normal, Vec3::new(-1.0, 0.0, 0.0));
+        assert_eq!(b.ray_interval(&ray), Some((4.0, 6.0)));
+        let inside = b.intersect_ray(&Ray::new(Vec3::zero(), Vec3::new(0.0, 0.0, 2.0))).unwrap();
+        assert_eq!(inside.t, 1.0);
+        assert_eq!(inside.normal, Vec3::unit_z());
+        assert!(b.intersect_ray(&Ray::new(Vec3::new(-5.0, 5.0, 0.0), Vec3::unit_x())).is_none());
+        assert!(b.intersect_ray(&Ray::new(Vec3::new(5.0, 0.0, 0.0), Vec3::unit_x())).is_none());
+        assert!(b.intersect_ray(&Ray::new(Vec3::zero(), Vec3::zero())).is_none());
+        assert!(Obb::from(b).intersect_ray(&Ray::new(Vec3::zero(), Vec3::zero())).is_none());
+    }
+
+    #[test]
+    fn obb_queries() {
+        // points of a box rotated about z, PCA recovers it
+        let q = Quat::new_from_angle_axis(30.0_f64.radians(), Vec3::unit_z());
+        let truth = Obb::new_from_quat(Vec3::new(1.0, 2.0, 3.0), Vec3::new(4.0, 2.0, 1.0), q);
+        let mut points = truth.corners().to_vec();
+        points.push(truth.center);
+        let fit = Obb::from_points(&points).unwrap();
+        assert!((fit.center - truth.center).length() < 0.000001);
+        assert!((fit.half_extents - truth.half_extents).length() < 0.000001);
+        assert!(fit.axis(0).cross(&truth.axis(0)).length() < 0.000001);
+        assert!(fit.rotation.is_rotation(0.000001));
+        assert!(fit.orientation().rotate_vec3(Vec3::unit_x()).cross(&truth.axis(0)).length() < 0.000001);
+        assert!(points.iter().all(|p| fit.contains_point(*p + (fit.center - *p).scalar_mul(0.000001))));
+
+        assert!(truth.contains_point(truth.center + truth.axis(0).scalar_mul(3.9)));
+        assert!(!truth.contains_point(truth.center + truth.axis(1).scalar_mul(2.1)));
+        let corner = truth.center + truth.axis(0).scalar_mul(5.0) + truth.axis(1).scalar_mul(3.0);
+        let expected = truth.center + truth.axis(0).scalar_mul(4.0) + truth.axis(1).scalar_mul(2.0);
+        assert!((truth.closest_point(corner) - expected).length() < 0.000001);
+
+        // separating axis tests, including an edge-edge separation
+        let near = truth.transform(Mat3::identity(), truth.axis(0).scalar_mul(7.0));
+        assert!(truth.intersects_obb(&near));
+        let far = truth.transform(Mat3::identity(), truth.axis(1).scalar_mul(4.5));
+        assert!(!truth.intersects_obb(&far));
+        let a = Obb::new(Vec3::zero(), Vec3::new(1.0, 1.0, 1.0), Mat3::identity());
+        let tilt = Mat3::new_from_angle_axis(45.0_f64.radians(), Vec3::unit_x()) * Mat3::new_from_angle_axis(45.0_f64.radians(), Vec3::unit_z());
+        let b = Obb::new(Vec3::new(2.5, 2.5, 0.0), Vec3::new(1.0, 1.0, 1.0), tilt);
+        assert_eq!(a.intersects_obb(&b), a.corners().iter().any(|p| b.contains_point(*p)) || b.corners().iter().any(|p| a.contains_point(*p)));
+        assert!(truth.intersects_aabb(&Aabb::new(Vec3::new(4.0, 4.0, 2.0), Vec3::new(6.0, 6.0, 4.0))));
+        assert!(truth.intersects_sphere(&Sphere::new(expected + truth.axis(0).scalar_mul(0.5), 1.0)));
+        assert!(truth.corners().iter().all(|p| truth.aabb().contains_point(*p + (truth.center - *p).scalar_mul(0.000001))));
+
+        let merged = a.merge(&a.transform(Mat3::identity(), Vec3::new(4.0, 0.0, 0.0)));
+        assert!((merged.half_extents[0] - 3.0).abs() < 0.000001);
+
+        let ray = Ray::new(truth.center - truth.axis(1).scalar_mul(10.0), truth.axis(1));
+        let hit = truth.intersect_ray(&ray).unwrap();
+        assert!((hit.t - 8.0).abs() < 0.000001);
+        assert!((hit.normal + truth.axis(1)).length() < 0.000001);
+    }
+
+    #[test]
+    fn bounding_spheres() {
+        let mut rng = Rng::new(11);
+        let points: Vec<Vec3> = (0..300).map(|_| sampling::uniform_sphere::<f64>(&mut rng).scalar_mul(rng.next_f64()) + Vec3::new(1.0, 1.0, 1.0)).collect();
+        let minimal = Sphere::from_points(&points).unwrap();
+        let ritter = Sphere::from_points_ritter(&points).unwrap();
+        assert!(points.iter().all(|p| minimal.contains_point(*p)));
+        assert!(points.iter().all(|p| ritter.contains_point(*p)));
+        assert!(minimal.radius <= ritter.radius + 0.000001);
+        assert!(minimal.radius <= 1.0);
+
+        // far from the origin in f32 the slack welzl accepts points with is large
+        let mut rng = Rng::new(12);
+        let far: Vec<Vec3f> = (0..500).map(|_| sampling::uniform_sphere::<f32>(&mut rng) * 1000.0 + Vec3f::new(5000.0, -3000.0, 200.0)).collect();
+        let s = Sphere::from_points(&far).unwrap();
+        assert!(far.iter().all(|p| s.contains_point(*p)));
+        assert!(s.radius < 1000.01);
+
+        // known answers: a tetrahedron and points on a circle
+        let tetra = [Vec3::new(1.0, 1.0, 1.0), Vec3::new(1.0, -1.0, -1.0), Vec3::new(-1.0, 1.0, -1.0), Vec3::new(-1.0, -1.0, 1.0)];
+        let s = Sphere::from_points(&tetra).unwrap();
+        assert!(s.center.length() < 0.000001 && (s.radius - 3.0_f64.sqrt()).abs() < 0.000001);
+        let circle: Vec<Vec3> = (0..12).map(|i| {
+            let a = i as f64 * 30.0_f64.radians();
+            Vec3::new(2.0 * a.cos(), 2.0 * a.sin(), 5.0)
+        }).collect();
+        let s = Sphere::from_points(&circle).unwrap();
+        assert!((s.center - Vec3::new(0.0, 0.0, 5.0)).length() < 0.000001 && (s.radius - 2.0).abs() < 0.000001);
+        assert_eq!(Sphere::from_points(&[Vec3::unit_x()]).unwrap().radius, 0.0);
+
+        let a = Sphere::new(Vec3::zero(), 1.0);
+        let b = Sphere::new(Vec3::new(4.0, 0.0, 0.0), 2.0);
+        let m = a.merge(&b);
+        assert!(m.contains_sphere(&a) && m.contains_sphere(&b));
+        assert!((m.radius - 3.5).abs() < 0.000001);
+        assert!(!a.intersects_sphere(&b) && a.intersects_sphere(&Sphere::new(Vec3::new(2.5, 0.0, 0.0), 2.0)));
+        assert!(a.intersects_aabb(&Aabb::new(Vec3::new(0.5, 0.5, 0.0), Vec3::new(2.0, 2.0, 2.0))));
+        let moved = b.transform(Mat3::new_from_angle_axis(90.0_f64.radians(), Vec3::unit_z()), Vec3::unit_z());
+        assert!((moved.center - Vec3::new(0.0, 4.0, 1.0)).length() < 0.000001);
+
+        let hit = b.intersect_ray(&Ray::new(Vec3::zero(), Vec3::unit_x())).unwrap();
+        assert_eq!(hit.t, 2.0);
+        assert_eq!(hit.normal, Vec3::new(-1.0, 0.0, 0.0));
+        let exit = b.intersect_ray(&Ray::new(b.center, Vec3::unit_x())).unwrap();
+        assert_eq!(exit.point, Vec3::new(6.0, 0.0, 0.0));
+        assert!(b.intersect_ray(&Ray::new(Vec3::zero(), Vec3::unit_z())).is_none());
+    }
+
+    #[test]
+    fn capsules() {
+        let c = Capsule::new(Vec3::zero(), Vec3::new(0.0, 0.0, 4.0), 1.0);
+        assert!(c.contains_point(Vec3::new(0.9, 0.0, 2.0)));
+        assert!(c.contains_point(Vec3::new(0.0, 0.0, 4.9)));
+        assert!(!c.contains_point(Vec3::new(0.8, 0.8, 4.8)));
+        assert_eq!(c.aabb(), Aabb::new(Vec3::new(-1.0, -1.0, -1.0), Vec3::new(1.0, 1.0, 5.0)));
+
+        let other = Capsule::new(Vec3::new(1.5, -3.0, 2.0), Vec3::new(1.5, 3.0, 2.0), 0.6);
+        assert!(c.intersects_capsule(&other));
+        assert!(!c.intersects_capsule(&other.transform(Mat3::identity(), Vec3::new(0.5, 0.0, 0.0))));
+        assert!(c.intersects_sphere(&Sphere::new(Vec3::new(0.0, 0.0, 6.0), 1.5)));
+
+        let m = c.merge(&other);
+        for p in [Vec3::new(0.0, 0.0, 5.0), Vec3::new(1.0, 0.0, 0.0), Vec3::new(1.5, 3.6, 2.0), Vec3::new(2.1, -3.0, 2.0)].iter() {
+            assert!(m.contains_point(*p));
+        }
+
+        let points: Vec<Vec3> = (0..50).map(|i| {
+            let a = i as f64 * 0.7;
+            Vec3::new(0.5 * a.cos(), 0.5 * a.sin(), i as f64 * 0.2)
+        }).collect();
+        let fit = Capsule::from_points(&points).unwrap();
+        assert!(points.iter().all(|p| fit.contains_point(*p)));
+        assert!(fit.segment.direction().unitize().z().abs() > 0.99);
+
+        // wall, cap and inside hits
+        let side = c.intersect_ray(&Ray::new(Vec3::new(-3.0, 0.0, 2.0), Vec3::unit_x())).unwrap();
+        assert!((side.t - 2.0).abs() < 0.000001);
+        assert!((side.normal - Vec3::new(-1.0, 0.0, 0.0)).length() < 0.000001);
+        let top = c.intersect_ray(&Ray::new(Vec3::new(0.0, 0.0, 10.0), Vec3::new(0.0, 0.0, -1.0))).unwrap();
+        assert!((top.t - 5.0).abs() < 0.000001);
+        assert!((top.normal - Vec3::unit_z()).length() < 0.000001);
+        let from_inside = c.intersect_ray(&Ray::new(Vec3::new(0.0, 0.0, 2.0), Vec3::new(0.0, 0.0, -1.0))).unwrap();
+        assert!((from_inside.point - Vec3::new(0.0, 0.0, -1.0)).length() < 0.000001);
+        assert!(c.intersect_ray(&Ray::new(Vec3::new(-3.0, 2.0, 2.0), Vec3::unit_x())).is_none());
+    }
+
+    fn random_triangles(rng: &mut Rng, count: usize) -> Vec<Triangle<f64>> {
+        (0..count).map(|_| {
+            let c = sampling::uniform_sphere::<f64>(rng) * (10.0 * rng.next_f64());
+            let a = c + sampling::uniform_sphere(rng) * 0.5;
+            let b = c + sampling::uniform_sphere(rng) * 0.5;
+            Triangle::new(a, b, c)
+        }).collect()
+    }
+
+    fn brute_closest(triangles: &[Triangle<f64>], ray: &Ray<f64>) -> Option<(usize, f64)> {
+        triangles.iter().enumerate()
+            .filter_map(|(i, t)| t.intersect_ray(ray, Culling::Disabled).map(|h| (i, h.t)))
+            .fold(None, |best: Option<(usize, f64)>, hit| match best {
+                Some(b) if b.1 <= hit.1 => best,
+                _ => Some(hit),
+            })
+    }
+
+    #[test]
+    fn bvh_ray_queries_match_brute_force() {
+        let mut rng = Rng::new(25);
+        let triangles = random_triangles(&mut rng, 500);
+        let bvh = Bvh::build(&triangles);
+        assert!(bvh.depth() < 30);
+        assert!(bvh.bounds().unwrap().contains_aabb(&triangles[42].aabb()));
+
+        let mut hits = 0;
+        for _ in 0..200 {
+            let origin = sampling::uniform_sphere::<f64>(&mut rng) * 15.0;
+            let target = sampling::uniform_sphere::<f64>(&mut rng) * 3.0;
+            let ray = Ray::new(origin, (target - origin).unitize());
+            let expected = brute_closest(&triangles, &ray);
+            let found = bvh.closest_triangle(&triangles, &ray, Culling::Disabled);
+            assert_eq!(found.map(|(i, _)| i), expected.map(|(i, _)| i));
+            if let (Some((_, hit)), Some((_, t))) = (found, expected) {
+                hits += 1;
+                assert!((hit.t - t).abs() < 0.000001);
+                // blocked before the nearest hit only by something nearer, which there is not
+                assert!(bvh.any_triangle(&triangles, &ray, t * 0.999, Culling::Disabled).is_none());
+                let blocker = bvh.any_triangle(&triangles, &ray, f64::INFINITY, Culling::Disabled).unwrap();
+                assert!(triangles[blocker].intersect_ray(&ray, Culling::Disabled).is_some());
+            } else {
+                assert!(bvh.any_triangle(&triangles, &ray, f64::INFINITY, Culling::Disabled).is_none());
+            }
+        }
+        assert!(hits > 20);
+
+        let empty: Bvh<f64> = Bvh::build::<Triangle<f64>>(&[]);
+        assert!(empty.is_empty());
+        assert!(empty.closest_triangle(&[], &Ray::new(Vec3::zero(), Vec3::unit_x()), Culling::Back).is_none());
+        assert!(empty.query_aabb(&Aabb::new(Vec3::new(-1.0, -1.0, -1.0), Vec3::new(1.0, 1.0, 1.0))).is_empty());
+    }
+
+    #[test]
+    fn bvh_overlap_queries_and_refit() {
+        let mut rng = Rng::new(26);
+        let mut triangles = random_triangles(&mut rng, 300);
+        let mut bvh = Bvh::build(&triangles);
+
+        let check = |bvh: &Bvh<f64>, triangles: &[Triangle<f64>]| {
+            let region = Aabb::new(Vec3::new(-2.0, -1.0, 0.0), Vec3::new(3.0, 4.0, 2.5));
+            let mut found = bvh.query_aabb(&region);
+            found.sort();
+            let expected: Vec<usize> = (0..triangles.len()).filter(|i| triangles[*i].aabb().intersects_aabb(&region)).collect();
+            assert_eq!(found, expected);
+
+            let sphere = Sphere::new(Vec3::new(1.0, -2.0, 1.0), 3.0);
+            let mut found = bvh.query_sphere(&sphere);
+            found.sort();
+            let expected: Vec<usize> = (0..triangles.len()).filter(|i| triangles[*i].aabb().intersects_sphere(&sphere)).collect();
+            assert!(!expected.is_empty());
+            assert_eq!(found, expected);
+        };
+        check(&bvh, &triangles);
+
+        // move every vertex, the refitted tree must answer like a fresh one
+        for t in triangles.iter_mut() {
+            let offset = sampling::uniform_sphere::<f64>(&mut rng) * 2.0;
+            *t = Triangle::new(t.a + offset, t.b + offset * 0.5, t.c - offset);
+        }
+        bvh.refit(&triangles);
+        check(&bvh, &triangles);
+        let ray = Ray::new(Vec3::new(-20.0, 0.1, 0.2), Vec3::unit_x());
+        assert_eq!(bvh.closest_triangle(&triangles, &ray, Culling::Disabled).map(|(i, _)| i),
+                   brute_closest(&triangles, &ray).map(|(i, _)| i));
+    }
+
+    #[test]
+    fn bvh_over_arbitrary_primitives() {
+        let spheres: Vec<Sphere<f64>> = (0..10).map(|i| Sphere::new(Vec3::new(i as f64 * 3.0, 0.0, 0.0), 1.0)).collect();
+        let bvh = Bvh::build(&spheres);
+        let ray = Ray::new(Vec3::new(40.0, 0.0, 0.0), -Vec3::unit_x());
+        let (i, t, hit) = bvh.closest_hit(&ray, |i, r| spheres[i].intersect_ray(r).map(|h| (h.t, h))).unwrap();
+        assert_eq!(i, 9);
+        assert!((t - 12.0).abs() < 0.000001);
+        assert!((hit.normal - Vec3::unit_x()).length() < 0.000001);
+        assert!(bvh.any_hit(&ray, 11.0, |i, r| spheres[i].intersect_ray(r).map(|h| h.t)).is_none());
+
+        let mut near = bvh.query_sphere(&Sphere::new(Vec3::new(4.5, 0.0, 0.0), 1.0));
+        near.sort();
+        assert_eq!(near, vec![1, 2]);
+
+        // points along an axis-aligned line: no box has any area, the tree still balances
+        let line: Vec<Vec3> = (0..1000).map(|i| Vec3::new(i as f64 * 0.5, 2.0, -1.0)).collect();
+        let bvh = Bvh::build(&line);
+        assert!(bvh.depth() <= 10);
+        let mut near = bvh.query_sphere(&Sphere::new(Vec3::new(100.0, 2.0, -1.0), 1.1));
+        near.sort();
+        assert_eq!(near, vec![198, 199, 200, 201, 202]);
+
+        let points = vec![Vec3::new(1.0, 1.0, 1.0); 20];
+        let degenerate = Bvh::build(&points);
+        assert_eq!(degenerate.query_aabb(&Aabb::new(Vec3::zero(), Vec3::new(1.0, 1.0, 1.0))).len(), 20);
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn bvh_parallel_build_matches_serial() {
+        let mut rng = Rng::new(27);
+        let triangles = random_triangles(&mut rng, 20000);
+        let serial = Bvh::build(&triangles);
+        let parallel = Bvh::build_parallel(&triangles, 3);
+        assert_eq!(serial.node_count(), parallel.node_count());
+        for _ in 0..50 {
+            let origin = sampling::uniform_sphere::<f64>(&mut rng) * 15.0;
+            let ray = Ray::new(origin, -origin.unitize());
+            assert_eq!(serial.closest_triangle(&triangles, &ray, Culling::Disabled).map(|(i, _)| i),
+                       parallel.closest_triangle(&triangles, &ray, Culling::Disabled).map(|(i, _)| i));
+        }
+    }
 }
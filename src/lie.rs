@@ -0,0 +1,339 @@
+use std::ops::{Add, Mul, Neg, Sub};
+use super::matrix3::Matrix3;
+use super::matrix_n::Matrix;
+use super::scalar::Float;
+use super::vector::Vector3;
+use super::vector_n::VectorN;
+
+// element of se(3), the tangent space of rigid transforms.
+// rho is the translational part and phi the rotational part (a rotation vector).
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Twist<T> {
+        pub rho: Vector3<T>,
+        pub phi: Vector3<T>,
+}
+
+impl<T: Float> Add for Twist<T> {
+        type Output = Twist<T>;
+
+        fn add(self, other: Twist<T>) -> Twist<T> {
+                Twist::new(self.rho + other.rho, self.phi + other.phi)
+        }
+}
+
+impl<T: Float> Sub for Twist<T> {
+        type Output = Twist<T>;
+
+        fn sub(self, other: Twist<T>) -> Twist<T> {
+                Twist::new(self.rho - other.rho, self.phi - other.phi)
+        }
+}
+
+impl<T: Float> Neg for Twist<T> {
+        type Output = Twist<T>;
+
+        fn neg(self) -> Twist<T> {
+                Twist::new(-self.rho, -self.phi)
+        }
+}
+
+impl<T: Float> Mul<T> for Twist<T> {
+        type Output = Twist<T>;
+
+        fn mul(self, s: T) -> Twist<T> {
+                Twist::new(self.rho * s, self.phi * s)
+        }
+}
+
+impl<T: Float> Twist<T> {
+        pub fn new(rho: Vector3<T>, phi: Vector3<T>) -> Twist<T> {
+                Twist { rho, phi }
+        }
+
+        pub fn zero() -> Twist<T> {
+                Twist::new(Vector3::zero(), Vector3::zero())
+        }
+
+        // (rho, phi) flattened
+        pub fn to_array(self) -> [T; 6] {
+                [self.rho.x(), self.rho.y(), self.rho.z(), self.phi.x(), self.phi.y(), self.phi.z()]
+        }
+
+        pub fn from_array(a: [T; 6]) -> Twist<T> {
+                Twist::new(Vector3::new(a[0], a[1], a[2]), Vector3::new(a[3], a[4], a[5]))
+        }
+}
+
+impl<T: Float> From<Twist<T>> for VectorN<T, 6> {
+        fn from(xi: Twist<T>) -> VectorN<T, 6> {
+                VectorN(xi.to_array())
+        }
+}
+
+impl<T: Float> From<VectorN<T, 6>> for Twist<T> {
+        fn from(v: VectorN<T, 6>) -> Twist<T> {
+                Twist::from_array(v.0)
+        }
+}
+
+// 6x6 operator on twists as 3x3 blocks, [[top left, top right], [bottom left, bottom right]]
+// acting on (rho, phi). Used for SE(3) adjoints and jacobians.
+#[derive(Debug, Copy, Clone)]
+pub struct Block6<T> {
+        pub blocks: [[Matrix3<T>; 2]; 2],
+}
+
+impl<T: Float> Mul<Twist<T>> for Block6<T> {
+        type Output = Twist<T>;
+
+        fn mul(self, xi: Twist<T>) -> Twist<T> {
+                let b = self.blocks;
+                Twist::new(b[0][0] * xi.rho + b[0][1] * xi.phi, b[1][0] * xi.rho + b[1][1] * xi.phi)
+        }
+}
+
+impl<T: Float> Mul for Block6<T> {
+        type Output = Block6<T>;
+
+        fn mul(self, other: Block6<T>) -> Block6<T> {
+                let a = self.blocks;
+                let b = other.blocks;
+                let mut blocks = [[Matrix3::zero(); 2]; 2];
+                for (r, row) in blocks.iter_mut().enumerate() {
+                        for (c, e) in row.iter_mut().enumerate() {
+                                *e = a[r][0] * b[0][c] + a[r][1] * b[1][c];
+                        }
+                }
+                Block6 { blocks }
+        }
+}
+
+impl<T: Float> Block6<T> {
+        pub fn new(top_left: Matrix3<T>, top_right: Matrix3<T>, bottom_left: Matrix3<T>, bottom_right: Matrix3<T>) -> Block6<T> {
+                Block6 { blocks: [[top_left, top_right], [bottom_left, bottom_right]] }
+        }
+
+        // row-major 6x6 entry
+        pub fn get(&self, r: usize, c: usize) -> T {
+                self.blocks[r / 3][c / 3][(r % 3, c % 3)]
+        }
+}
+
+impl<T: Float> From<Block6<T>> for Matrix<T, 6, 6> {
+        fn from(b: Block6<T>) -> Matrix<T, 6, 6> {
+                let mut m = Matrix::zero();
+                for (r, row) in m.v.iter_mut().enumerate() {
+                        for (c, e) in row.iter_mut().enumerate() {
+                                *e = b.get(r, c);
+                        }
+                }
+                m
+        }
+}
+
+pub mod so3 {
+        use super::super::matrix3::Matrix3;
+        use super::super::quaternion::Quaternion;
+        use super::super::scalar::Float;
+        use super::super::vector::Vector3;
+
+        // below this angle the closed forms are replaced by their taylor expansion
+        fn small<T: Float>() -> T {
+                T::epsilon().sqrt()
+        }
+
+        // the jacobian coefficients cancel like theta^2 up to theta^5 in closed form, far
+        // above small(). Below this angle (about 0.26 for f64, 0.92 for f32) their series
+        // through theta^6 is accurate to rounding while the closed forms are not.
+        pub(super) fn series_limit<T: Float>() -> T {
+                T::epsilon().sqrt().sqrt().sqrt().sqrt() * T::from_f64(2.5)
+        }
+
+        // c0 + c1 theta^2 + c2 theta^4 + c3 theta^6
+        pub(super) fn series<T: Float>(theta2: T, c: [f64; 4]) -> T {
+                c.iter().rev().fold(T::zero(), |acc, ci| acc * theta2 + T::from_f64(*ci))
+        }
+
+        // skew-symmetric matrix with hat(w) * v == w x v
+        pub fn hat<T: Float>(w: Vector3<T>) -> Matrix3<T> {
+                let zero = T::zero();
+                let v = [
+                        zero, -w.z(), w.y(),
+                        w.z(), zero, -w.x(),
+                        -w.y(), w.x(), zero ];
+                Matrix3 { v }
+        }
+
+        // inverse of hat, reads the skew-symmetric part of m
+        pub fn vee<T: Float>(m: Matrix3<T>) -> Vector3<T> {
+                let half = T::from_f64(0.5);
+                Vector3::new(
+                        (m.m21() - m.m12()) * half,
+                        (m.m02() - m.m20()) * half,
+                        (m.m10() - m.m01()) * half)
+        }
+
+        // rotation vector to rotation matrix (Rodrigues)
+        pub fn exp<T: Float>(w: Vector3<T>) -> Matrix3<T> {
+                let theta = w.length();
+                if theta < small() {
+                        let k = hat(w);
+                        return Matrix3::identity() + k + k * k * T::from_f64(0.5);
+                }
+                Matrix3::new_from_angle_axis(theta, w)
+        }
+
+        pub fn exp_quat<T: Float>(w: Vector3<T>) -> Quaternion<T> {
+                Quaternion::new_from_rotation_vector(w)
+        }
+
+        // rotation matrix to rotation vector with angle in [0, pi].
+        // goes through the quaternion so it stays accurate near pi, where the
+        // skew-symmetric part of r vanishes.
+        pub fn log<T: Float>(r: Matrix3<T>) -> Vector3<T> {
+                Quaternion::from(r).to_rotation_vector()
+        }
+
+        pub fn log_quat<T: Float>(q: Quaternion<T>) -> Vector3<T> {
+                q.unitize().to_rotation_vector()
+        }
+
+        // the adjoint of SO(3) is the rotation itself: r * exp(w) * r^T = exp(r * w)
+        pub fn adjoint<T: Float>(r: Matrix3<T>) -> Matrix3<T> {
+                r
+        }
+
+        // exp(w + dw) ~ exp(left_jacobian(w) * dw) * exp(w)
+        pub fn left_jacobian<T: Float>(w: Vector3<T>) -> Matrix3<T> {
+                let theta = w.length();
+                let k = hat(w);
+                let kk = k * k;
+                let theta2 = theta * theta;
+                let (a, b) = if theta < series_limit() {
+                        (
+                                series(theta2, [1.0 / 2.0, -1.0 / 24.0, 1.0 / 720.0, -1.0 / 40320.0]),
+                                series(theta2, [1.0 / 6.0, -1.0 / 120.0, 1.0 / 5040.0, -1.0 / 362880.0]),
+                        )
+                } else {
+                        ((T::one() - theta.cos()) / theta2, (theta - theta.sin()) / (theta2 * theta))
+                };
+                Matrix3::identity() + k * a + kk * b
+        }
+
+        pub fn left_jacobian_inv<T: Float>(w: Vector3<T>) -> Matrix3<T> {
+                let theta = w.length();
+                let k = hat(w);
+                let kk = k * k;
+                let half = T::from_f64(0.5);
+                let theta2 = theta * theta;
+                let c = if theta < series_limit() {
+                        series(theta2, [1.0 / 12.0, 1.0 / 720.0, 1.0 / 30240.0, 1.0 / 1209600.0])
+                } else {
+                        T::one() / theta2 - (T::one() + theta.cos()) / (T::from_f64(2.0) * theta * theta.sin())
+                };
+                Matrix3::identity() - k * half + kk * c
+        }
+
+        // exp(w + dw) ~ exp(w) * exp(right_jacobian(w) * dw)
+        pub fn right_jacobian<T: Float>(w: Vector3<T>) -> Matrix3<T> {
+                left_jacobian(-w)
+        }
+
+        pub fn right_jacobian_inv<T: Float>(w: Vector3<T>) -> Matrix3<T> {
+                left_jacobian_inv(-w)
+        }
+}
+
+// SE(3) elements are homogeneous Matrix4 values with a pure rotation in the upper-left block
+pub mod se3 {
+        use super::{Block6, Twist};
+        use super::so3;
+        use super::super::matrix3::Matrix3;
+        use super::super::matrix4::Matrix4;
+        use super::super::scalar::Float;
+
+        pub fn hat<T: Float>(xi: Twist<T>) -> Matrix4<T> {
+                let mut m = Matrix4::from(so3::hat(xi.phi));
+                m.v[3] = xi.rho.x();
+                m.v[7] = xi.rho.y();
+                m.v[11] = xi.rho.z();
+                m.v[15] = T::zero();
+                m
+        }
+
+        pub fn vee<T: Float>(m: Matrix4<T>) -> Twist<T> {
+                Twist::new(m.translation(), so3::vee(m.linear()))
+        }
+
+        pub fn exp<T: Float>(xi: Twist<T>) -> Matrix4<T> {
+                let r = so3::exp(xi.phi);
+                let t = so3::left_jacobian(xi.phi) * xi.rho;
+                Matrix4::new_translation(t) * Matrix4::from(r)
+        }
+
+        pub fn log<T: Float>(m: Matrix4<T>) -> Twist<T> {
+                let phi = so3::log(m.linear());
+                let rho = so3::left_jacobian_inv(phi) * m.translation();
+                Twist::new(rho, phi)
+        }
+
+        // m * exp(xi) * m^-1 = exp(adjoint(m) * xi)
+        pub fn adjoint<T: Float>(m: Matrix4<T>) -> Block6<T> {
+                let r = m.linear();
+                Block6::new(r, so3::hat(m.translation()) * r, Matrix3::zero(), r)
+        }
+
+        // translational coupling block of the SE(3) jacobian (Barfoot, eq. 7.86)
+        fn q_block<T: Float>(xi: Twist<T>) -> Matrix3<T> {
+                let theta = xi.phi.length();
+                let p = so3::hat(xi.phi);
+                let r = so3::hat(xi.rho);
+                let pr = p * r;
+                let rp = r * p;
+                let prp = pr * p;
+                let pp = p * p;
+
+                let t2 = theta * theta;
+                let (a, b, c) = if theta < so3::series_limit() {
+                        (
+                                so3::series(t2, [1.0 / 6.0, -1.0 / 120.0, 1.0 / 5040.0, -1.0 / 362880.0]),
+                                so3::series(t2, [1.0 / 24.0, -1.0 / 720.0, 1.0 / 40320.0, -1.0 / 3628800.0]),
+                                so3::series(t2, [1.0 / 120.0, -1.0 / 2520.0, 1.0 / 120960.0, -1.0 / 9979200.0]),
+                        )
+                } else {
+                        let two = T::from_f64(2.0);
+                        let (s, co) = (theta.sin(), theta.cos());
+                        (
+                                (theta - s) / (t2 * theta),
+                                (t2 + two * co - two) / (two * t2 * t2),
+                                (two * theta - T::from_f64(3.0) * s + theta * co) / (two * t2 * t2 * theta),
+                        )
+                };
+
+                r * T::from_f64(0.5)
+                        + (pr + rp + prp) * a
+                        + (pp * r + rp * p - prp * T::from_f64(3.0)) * b
+                        + (prp * p + pp * rp) * c
+        }
+
+        // exp(xi + dxi) ~ exp(left_jacobian(xi) * dxi) * exp(xi)
+        pub fn left_jacobian<T: Float>(xi: Twist<T>) -> Block6<T> {
+                let j = so3::left_jacobian(xi.phi);
+                Block6::new(j, q_block(xi), Matrix3::zero(), j)
+        }
+
+        pub fn left_jacobian_inv<T: Float>(xi: Twist<T>) -> Block6<T> {
+                let j_inv = so3::left_jacobian_inv(xi.phi);
+                let q = q_block(xi);
+                Block6::new(j_inv, -(j_inv * q * j_inv), Matrix3::zero(), j_inv)
+        }
+
+        // exp(xi + dxi) ~ exp(xi) * exp(right_jacobian(xi) * dxi)
+        pub fn right_jacobian<T: Float>(xi: Twist<T>) -> Block6<T> {
+                left_jacobian(-xi)
+        }
+
+        pub fn right_jacobian_inv<T: Float>(xi: Twist<T>) -> Block6<T> {
+                left_jacobian_inv(-xi)
+        }
+}
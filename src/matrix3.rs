@@ -1,86 +1,258 @@
-use std::ops::Mul;
+use std::ops::{Add, Index, IndexMut, Mul, MulAssign, Neg, Sub};
 use std::convert::From;
-use super::quaternion::Quat;
-use super::vector::Vec3;
+use super::matrix_n::Matrix;
+use super::quaternion::Quaternion;
+use super::vector::Vector3;
+use super::scalar::Float;
+use super::euler::{EulerFrame, EulerOrder};
 
 #[derive(Debug, Clone, Copy)]
-pub struct Mat3 {
-        pub v: [f64; 9]
+pub struct Matrix3<T> {
+        pub v: [T; 9]
 }
 
-impl From<Quat> for Mat3 {
-    fn from(o: Quat) -> Mat3 {
+pub type Mat3 = Matrix3<f64>;
+pub type Mat3f = Matrix3<f32>;
+
+impl<T: Float> From<Quaternion<T>> for Matrix3<T> {
+    fn from(o: Quaternion<T>) -> Matrix3<T> {
+        let two = T::from_f64(2.0);
         let v = [
             o.w().powi(2) + o.x().powi(2) - o.y().powi(2) - o.z().powi(2),
-            2.0 * (o.x() * o.y() - o.w() * o.z()),
-            2.0 * (o.x() * o.z() + o.w() * o.y()),
-            2.0 * (o.x() * o.y() + o.w() * o.z()),
+            two * (o.x() * o.y() - o.w() * o.z()),
+            two * (o.x() * o.z() + o.w() * o.y()),
+            two * (o.x() * o.y() + o.w() * o.z()),
             o.w().powi(2) - o.x().powi(2) + o.y().powi(2) - o.z().powi(2),
-            2.0 * (o.y() * o.z() - o.w() * o.x()),
-            2.0 * (o.x() * o.z() - o.w() * o.y()),
-            2.0 * (o.y() * o.z() + o.w() * o.x()),
+            two * (o.y() * o.z() - o.w() * o.x()),
+            two * (o.x() * o.z() - o.w() * o.y()),
+            two * (o.y() * o.z() + o.w() * o.x()),
             o.w().powi(2) - o.x().powi(2) - o.y().powi(2) + o.z().powi(2)
         ];
 
-        Mat3{v}
+        Matrix3{v}
     }
 }
 
-impl Mul for Mat3 {
-        type Output = Mat3;
+impl<T: Float> Mul for Matrix3<T> {
+        type Output = Matrix3<T>;
 
-        fn mul(self, other: Mat3) -> Mat3 {
-                let mut r = Mat3::zero();
+        fn mul(self, other: Matrix3<T>) -> Matrix3<T> {
+                Matrix3::from(Matrix::from(self) * Matrix::from(other))
+        }
+}
 
-                r.v[0] = self.m00() * other.m00()
-                        + self.m01() * other.m10()
-                        + self.m02() * other.m20();
+impl<T: Float> Mul<Vector3<T>> for Matrix3<T> {
+        type Output = Vector3<T>;
 
-                r.v[1] = self.m00() * other.m01()
-                        + self.m01() * other.m11()
-                        + self.m02() * other.m21();
+        fn mul(self, o: Vector3<T>) -> Vector3<T> {
+                o.apply_rot_mat3(self)
+        }
+}
 
-                r.v[2] = self.m00() * other.m02()
-                        + self.m01() * other.m12()
-                       + self.m02() * other.m22();
+impl<T: Float> Mul<T> for Matrix3<T> {
+        type Output = Matrix3<T>;
 
-                r.v[3] = self.m10() * other.m00()
-                        + self.m11() * other.m10()
-                        + self.m12() * other.m20();
+        fn mul(self, s: T) -> Matrix3<T> {
+                let mut r = self;
+                for e in r.v.iter_mut() {
+                        *e *= s;
+                }
+                r
+        }
+}
 
-                r.v[4] = self.m10() * other.m01()
-                        + self.m11() * other.m11()
-                        + self.m12() * other.m21();
+impl<T: Float> MulAssign for Matrix3<T> {
+        fn mul_assign(&mut self, other: Matrix3<T>) {
+                *self = *self * other;
+        }
+}
 
-                r.v[5] = self.m10() * other.m02()
-                        + self.m11() * other.m12()
-                        + self.m12() * other.m22();
+impl<T: Float> Add for Matrix3<T> {
+        type Output = Matrix3<T>;
 
-                r.v[6] = self.m20() * other.m00()
-                        + self.m21() * other.m10()
-                        + self.m22() * other.m20();
+        fn add(self, other: Matrix3<T>) -> Matrix3<T> {
+                let mut r = self;
+                for (e, o) in r.v.iter_mut().zip(other.v.iter()) {
+                        *e += *o;
+                }
+                r
+        }
+}
 
-                r.v[7] = self.m20() * other.m01()
-                        + self.m21() * other.m11()
-                        + self.m22() * other.m21();
+impl<T: Float> Sub for Matrix3<T> {
+        type Output = Matrix3<T>;
 
-                r.v[8] = self.m20() * other.m02()
-                        + self.m21() * other.m12()
-                        + self.m22() * other.m22();
+        fn sub(self, other: Matrix3<T>) -> Matrix3<T> {
+                let mut r = self;
+                for (e, o) in r.v.iter_mut().zip(other.v.iter()) {
+                        *e -= *o;
+                }
                 r
         }
 }
 
-impl Mat3 {
-        pub fn zero() -> Mat3 {
+impl<T: Float> Neg for Matrix3<T> {
+        type Output = Matrix3<T>;
+
+        fn neg(self) -> Matrix3<T> {
+                self * -T::one()
+        }
+}
+
+// m[(row, col)]
+impl<T: Float> Index<(usize, usize)> for Matrix3<T> {
+        type Output = T;
+
+        fn index(&self, (r, c): (usize, usize)) -> &T {
+                assert!(r < 3 && c < 3, "index ({}, {}) out of range for Matrix3", r, c);
+                &self.v[r * 3 + c]
+        }
+}
+
+impl<T: Float> IndexMut<(usize, usize)> for Matrix3<T> {
+        fn index_mut(&mut self, (r, c): (usize, usize)) -> &mut T {
+                assert!(r < 3 && c < 3, "index ({}, {}) out of range for Matrix3", r, c);
+                &mut self.v[r * 3 + c]
+        }
+}
+
+impl From<Matrix3<f32>> for Matrix3<f64> {
+        fn from(o: Matrix3<f32>) -> Matrix3<f64> {
+                o.cast()
+        }
+}
+
+impl<T: Float> Matrix3<T> {
+        pub fn zero() -> Matrix3<T> {
+                let v = [T::zero(); 9];
+                Matrix3 { v }
+        }
+
+        pub fn identity() -> Matrix3<T> {
+                let mut r = Matrix3::zero();
+                r.v[0] = T::one();
+                r.v[4] = T::one();
+                r.v[8] = T::one();
+                r
+        }
+
+        // converts between precisions, rounding when narrowing to f32
+        pub fn cast<U: Float>(&self) -> Matrix3<U> {
+                let mut r = Matrix3::zero();
+                for (e, o) in r.v.iter_mut().zip(self.v.iter()) {
+                        *e = U::from_f64(o.to_f64());
+                }
+                r
+        }
+
+        pub fn new_from_cols(c0: Vector3<T>, c1: Vector3<T>, c2: Vector3<T>) -> Matrix3<T> {
                 let v = [
-                        0.0, 0.0, 0.0,
-                        0.0, 0.0, 0.0,
-                        0.0, 0.0, 0.0 ];               
-                Mat3 { v }
+                        c0.x(), c1.x(), c2.x(),
+                        c0.y(), c1.y(), c2.y(),
+                        c0.z(), c1.z(), c2.z() ];
+                Matrix3 { v }
+        }
+
+        pub fn col(&self, i: usize) -> Vector3<T> {
+                Vector3::new(self.v[i], self.v[3 + i], self.v[6 + i])
         }
 
-        pub fn new_from_angle_axis(rad: f64, axis: Vec3) -> Mat3 {
+        pub fn row(&self, i: usize) -> Vector3<T> {
+                Vector3::new(self.v[3 * i], self.v[3 * i + 1], self.v[3 * i + 2])
+        }
+
+        pub fn transpose(&self) -> Matrix3<T> {
+                let v = [
+                        self.m00(), self.m10(), self.m20(),
+                        self.m01(), self.m11(), self.m21(),
+                        self.m02(), self.m12(), self.m22() ];
+                Matrix3 { v }
+        }
+
+        pub fn trace(&self) -> T {
+                self.m00() + self.m11() + self.m22()
+        }
+
+        pub fn determinant(&self) -> T {
+                self.m00() * (self.m11() * self.m22() - self.m12() * self.m21())
+                        - self.m01() * (self.m10() * self.m22() - self.m12() * self.m20())
+                        + self.m02() * (self.m10() * self.m21() - self.m11() * self.m20())
+        }
+
+        pub fn adjugate(&self) -> Matrix3<T> {
+                let v = [
+                        self.m11() * self.m22() - self.m12() * self.m21(),
+                        self.m02() * self.m21() - self.m01() * self.m22(),
+                        self.m01() * self.m12() - self.m02() * self.m11(),
+                        self.m12() * self.m20() - self.m10() * self.m22(),
+                        self.m00() * self.m22() - self.m02() * self.m20(),
+                        self.m02() * self.m10() - self.m00() * self.m12(),
+                        self.m10() * self.m21() - self.m11() * self.m20(),
+                        self.m01() * self.m20() - self.m00() * self.m21(),
+                        self.m00() * self.m11() - self.m01() * self.m10() ];
+                Matrix3 { v }
+        }
+
+        // returns None when the matrix is singular (or too close to it),
+        // see Matrix::negligible_determinant
+        pub fn try_inverse(&self) -> Option<Matrix3<T>> {
+                let det = self.determinant();
+                if Matrix::from(*self).negligible_determinant(det) {
+                        return None;
+                }
+                let mut r = self.adjugate();
+                for e in r.v.iter_mut() {
+                        *e /= det;
+                }
+                Some(r)
+        }
+
+        // true when the matrix is orthonormal with determinant +1 (a proper rotation)
+        pub fn is_rotation(&self, tolerance: T) -> bool {
+                let rtr = self.transpose() * *self;
+                let id = Matrix3::identity();
+                rtr.v.iter().zip(id.v.iter()).all(|(a, b)| (*a - *b).abs() < tolerance)
+                        && (self.determinant() - T::one()).abs() < tolerance
+        }
+
+        // Gram-Schmidt on the columns, pulls a drifted rotation back onto SO(3).
+        // The first column keeps its direction, the third is rebuilt as a cross product.
+        pub fn orthonormalize(&self) -> Matrix3<T> {
+                let x = self.col(0).unitize();
+                let y = self.col(1);
+                let y = (y - x.scalar_mul(x.dot(&y))).unitize();
+                let z = x.cross(&y);
+                Matrix3::new_from_cols(x, y, z)
+        }
+
+        // closest rotation in the least-squares sense (polar decomposition),
+        // computed with the Newton iteration R <- (R + R^-T) / 2.
+        // Unlike Gram-Schmidt it spreads the correction over all three axes.
+        pub fn orthonormalize_polar(&self) -> Matrix3<T> {
+                let half = T::from_f64(0.5);
+                let mut r = *self;
+                for _ in 0..20 {
+                        let inv_t = match r.try_inverse() {
+                                Some(inv) => inv.transpose(),
+                                None => return self.orthonormalize(),
+                        };
+                        let mut next = r;
+                        for (e, i) in next.v.iter_mut().zip(inv_t.v.iter()) {
+                                *e = (*e + *i) * half;
+                        }
+                        let delta = next.v.iter().zip(r.v.iter())
+                                .fold(T::zero(), |m, (a, b)| m.max((*a - *b).abs()));
+                        r = next;
+                        // convergence is quadratic, the next step would be below epsilon
+                        if delta < T::epsilon().sqrt() {
+                                break;
+                        }
+                }
+                r
+        }
+
+        pub fn new_from_angle_axis(rad: T, axis: Vector3<T>) -> Matrix3<T> {
+            let one = T::one();
             let c = rad.cos();
             let s = rad.sin();
             let a = axis.unitize();
@@ -89,35 +261,29 @@ impl Mat3 {
             let z = a.z();
 
             let v = [
-                c + (1.0 - c) * x.powi(2),
-                (1.0 - c) * x * y - s * z,
-                (1.0 - c) * x * z + s * y,
-                (1.0 - c) * x * y + s * z,
-                c + (1.0 - c) * y.powi(2),
-                (1.0 - c) * y * z - s * x,
-                (1.0 - c) * x * z - s * y,
-                (1.0 - c) * y * z + s * x,
-                c + (1.0 - c) * z.powi(2)
+                c + (one - c) * x.powi(2),
+                (one - c) * x * y - s * z,
+                (one - c) * x * z + s * y,
+                (one - c) * x * y + s * z,
+                c + (one - c) * y.powi(2),
+                (one - c) * y * z - s * x,
+                (one - c) * x * z - s * y,
+                (one - c) * y * z + s * x,
+                c + (one - c) * z.powi(2)
             ];
-            Mat3{v}
+            Matrix3{v}
         }
 
-        pub fn yaw_pitch_roll(&self) -> (f64, f64, f64) {
+        pub fn yaw_pitch_roll(&self) -> (T, T, T) {
             // z -> y -> x
-            let beta =  (-self.m20()).asin(); // pitch
-            let gamma = (self.m21() / beta.cos()).asin();// yaw
-            let alpha = (self.m00() / beta.cos()).acos();// roll
+            let (alpha, beta, gamma) = self.to_euler(EulerOrder::ZYX, EulerFrame::Intrinsic);
 
             (gamma, beta, alpha)
         }
 
-        pub fn eular_angle_zyz(&self) -> (f64, f64, f64) {
-            let theta = self.m22().acos(); // no.2
-            let sin_theta = theta.sin();
-            let phi = (self.m12()/sin_theta).asin(); // no.1
-            let psi = (self.m21()/sin_theta).asin(); // no.3
-
-            (phi, theta, psi)
+        pub fn eular_angle_zyz(&self) -> (T, T, T) {
+            // phi, theta, psi
+            self.to_euler(EulerOrder::ZYZ, EulerFrame::Intrinsic)
         }
 
         //FIXME: impl for fmt
@@ -141,31 +307,31 @@ impl Mat3 {
                 );
         }
 
-        pub fn m00(&self) -> f64 {
+        pub fn m00(&self) -> T {
                 self.v[0]
         }
-        pub fn m01(&self) -> f64 {
+        pub fn m01(&self) -> T {
                 self.v[1]
         }
-        pub fn m02(&self) -> f64 {
+        pub fn m02(&self) -> T {
                 self.v[2]
         }
-        pub fn m10(&self) -> f64 {
+        pub fn m10(&self) -> T {
                 self.v[3]
         }
-        pub fn m11(&self) -> f64 {
+        pub fn m11(&self) -> T {
                 self.v[4]
         }
-        pub fn m12(&self) -> f64 {
+        pub fn m12(&self) -> T {
                 self.v[5]
         }
-        pub fn m20(&self) -> f64 {
+        pub fn m20(&self) -> T {
                 self.v[6]
         }
-        pub fn m21(&self) -> f64 {
+        pub fn m21(&self) -> T {
                 self.v[7]
         }
-        pub fn m22(&self) -> f64 {
+        pub fn m22(&self) -> T {
                 self.v[8]
         }
 }
@@ -1,114 +1,403 @@
-use std::ops::Mul;
-
-pub struct Mat4 {
-        pub v: [f64; 16],
-}
-
-impl Mul for Mat4 {
-        type Output = Mat4;
-
-        fn mul(self, other: Mat4) -> Mat4 {
-                let mut r = Mat4::zero();
-
-                r.v[0] = self.m00() * other.m00()
-                        + self.m01() * other.m10()
-                        + self.m02() * other.m20()
-                        + self.m03() * other.m30();
-
-                r.v[1] = self.m00() * other.m01()
-                        + self.m01() * other.m11()
-                        + self.m02() * other.m21()
-                        + self.m03() * other.m31();
-
-                r.v[2] = self.m00() * other.m02()
-                        + self.m01() * other.m12()
-                        + self.m02() * other.m22()
-                        + self.m03() * other.m32();
-
-                r.v[3] = self.m00() * other.m03()
-                        + self.m01() * other.m13()
-                        + self.m02() * other.m23()
-                        + self.m03() * other.m33();
-
-                r.v[4] = self.m10() * other.m00()
-                        + self.m11() * other.m10()
-                        + self.m12() * other.m20()
-                        + self.m13() * other.m30();
-
-                r.v[5] = self.m10() * other.m01()
-                        + self.m11() * other.m11()
-                        + self.m12() * other.m21()
-                        + self.m13() * other.m31();
-
-                r.v[6] = self.m10() * other.m02()
-                        + self.m11() * other.m12()
-                        + self.m12() * other.m22()
-                        + self.m13() * other.m32();
-
-                r.v[7] = self.m10() * other.m03()
-                        + self.m11() * other.m13()
-                        + self.m12() * other.m23()
-                        + self.m13() * other.m33();
-
-                r.v[8] = self.m20() * other.m00()
-                        + self.m21() * other.m10()
-                        + self.m22() * other.m20()
-                        + self.m23() * other.m30();
-
-                r.v[9] = self.m20() * other.m01()
-                        + self.m21() * other.m11()
-                        + self.m22() * other.m21()
-                        + self.m23() * other.m31();
-
-                r.v[10] = self.m20() * other.m02()
-                        + self.m21() * other.m12()
-                        + self.m22() * other.m22()
-                        + self.m23() * other.m32();
-
-                r.v[11] = self.m20() * other.m03()
-                        + self.m21() * other.m13()
-                        + self.m22() * other.m23()
-                        + self.m23() * other.m33();
-
-                r.v[12] = self.m30() * other.m00()
-                        + self.m31() * other.m10()
-                        + self.m32() * other.m20()
-                        + self.m33() * other.m30();
-
-                r.v[13] = self.m30() * other.m01()
-                        + self.m31() * other.m11()
-                        + self.m32() * other.m21()
-                        + self.m33() * other.m31();
-
-                r.v[14] = self.m30() * other.m02()
-                        + self.m31() * other.m12()
-                        + self.m32() * other.m22()
-                        + self.m33() * other.m32();
-
-                r.v[15] = self.m30() * other.m03()
-                        + self.m31() * other.m13()
-                        + self.m32() * other.m23()
-                        + self.m33() * other.m33();
+use std::ops::{Add, Index, IndexMut, Mul, MulAssign, Neg, Sub};
+use std::convert::From;
+use super::matrix3::Matrix3;
+use super::matrix_n::Matrix;
+use super::vector::{Vector3, Vector4};
+use super::vector_n::VectorN;
+use super::scalar::Float;
 
+// which way the camera looks down its z axis in view space:
+// right handed looks toward -z (OpenGL), left handed toward +z (Direct3D)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Handedness {
+        Right,
+        Left,
+}
+
+// depth range of normalized device coordinates after the perspective divide
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DepthRange {
+        // -1 at the near plane, 1 at the far plane (OpenGL)
+        NegOneToOne,
+        // 0 at the near plane, 1 at the far plane (Direct3D, Vulkan, Metal)
+        ZeroToOne,
+}
+
+impl Handedness {
+        // sign of the view direction along z
+        fn z_sign<T: Float>(self) -> T {
+                match self {
+                        Handedness::Right => -T::one(),
+                        Handedness::Left => T::one(),
+                }
+        }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Matrix4<T> {
+        pub v: [T; 16],
+}
+
+pub type Mat4 = Matrix4<f64>;
+pub type Mat4f = Matrix4<f32>;
+
+impl<T: Float> Mul for Matrix4<T> {
+        type Output = Matrix4<T>;
+
+        fn mul(self, other: Matrix4<T>) -> Matrix4<T> {
+                Matrix4::from(Matrix::from(self) * Matrix::from(other))
+        }
+}
+
+impl<T: Float> Mul<Vector4<T>> for Matrix4<T> {
+        type Output = Vector4<T>;
+
+        fn mul(self, o: Vector4<T>) -> Vector4<T> {
+                Vector4::from(Matrix::from(self) * VectorN::from(o))
+        }
+}
+
+impl<T: Float> Mul<T> for Matrix4<T> {
+        type Output = Matrix4<T>;
+
+        fn mul(self, s: T) -> Matrix4<T> {
+                let mut r = self;
+                for e in r.v.iter_mut() {
+                        *e *= s;
+                }
                 r
         }
 }
 
-impl Mat4 {
-        // fn identity() -> Mat4 {
-        //         let v = [
-        //                 1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0,
-        //                 1.0,
-        //         ];
-        //         Mat4 { v }
-        // }
+impl<T: Float> MulAssign for Matrix4<T> {
+        fn mul_assign(&mut self, other: Matrix4<T>) {
+                *self = *self * other;
+        }
+}
+
+impl<T: Float> Add for Matrix4<T> {
+        type Output = Matrix4<T>;
+
+        fn add(self, other: Matrix4<T>) -> Matrix4<T> {
+                let mut r = self;
+                for (e, o) in r.v.iter_mut().zip(other.v.iter()) {
+                        *e += *o;
+                }
+                r
+        }
+}
 
-        pub fn zero() -> Mat4 {
+impl<T: Float> Sub for Matrix4<T> {
+        type Output = Matrix4<T>;
+
+        fn sub(self, other: Matrix4<T>) -> Matrix4<T> {
+                let mut r = self;
+                for (e, o) in r.v.iter_mut().zip(other.v.iter()) {
+                        *e -= *o;
+                }
+                r
+        }
+}
+
+impl<T: Float> Neg for Matrix4<T> {
+        type Output = Matrix4<T>;
+
+        fn neg(self) -> Matrix4<T> {
+                self * -T::one()
+        }
+}
+
+// m[(row, col)]
+impl<T: Float> Index<(usize, usize)> for Matrix4<T> {
+        type Output = T;
+
+        fn index(&self, (r, c): (usize, usize)) -> &T {
+                assert!(r < 4 && c < 4, "index ({}, {}) out of range for Matrix4", r, c);
+                &self.v[r * 4 + c]
+        }
+}
+
+impl<T: Float> IndexMut<(usize, usize)> for Matrix4<T> {
+        fn index_mut(&mut self, (r, c): (usize, usize)) -> &mut T {
+                assert!(r < 4 && c < 4, "index ({}, {}) out of range for Matrix4", r, c);
+                &mut self.v[r * 4 + c]
+        }
+}
+
+impl<T: Float> From<Matrix3<T>> for Matrix4<T> {
+        fn from(o: Matrix3<T>) -> Matrix4<T> {
+                let zero = T::zero();
                 let v = [
-                        0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0,
-                        0.0,
+                        o.m00(), o.m01(), o.m02(), zero,
+                        o.m10(), o.m11(), o.m12(), zero,
+                        o.m20(), o.m21(), o.m22(), zero,
+                        zero, zero, zero, T::one(),
                 ];
-                Mat4 { v }
+                Matrix4 { v }
+        }
+}
+
+impl From<Matrix4<f32>> for Matrix4<f64> {
+        fn from(o: Matrix4<f32>) -> Matrix4<f64> {
+                o.cast()
+        }
+}
+
+impl<T: Float> Matrix4<T> {
+        pub fn identity() -> Matrix4<T> {
+                let mut r = Matrix4::zero();
+                r.v[0] = T::one();
+                r.v[5] = T::one();
+                r.v[10] = T::one();
+                r.v[15] = T::one();
+                r
+        }
+
+        pub fn zero() -> Matrix4<T> {
+                let v = [T::zero(); 16];
+                Matrix4 { v }
+        }
+
+        // converts between precisions, rounding when narrowing to f32
+        pub fn cast<U: Float>(&self) -> Matrix4<U> {
+                let mut r = Matrix4::zero();
+                for (e, o) in r.v.iter_mut().zip(self.v.iter()) {
+                        *e = U::from_f64(o.to_f64());
+                }
+                r
+        }
+
+        pub fn new_translation(t: Vector3<T>) -> Matrix4<T> {
+                let mut r = Matrix4::identity();
+                r.v[3] = t.x();
+                r.v[7] = t.y();
+                r.v[11] = t.z();
+                r
+        }
+
+        pub fn new_scale(s: Vector3<T>) -> Matrix4<T> {
+                let mut r = Matrix4::identity();
+                r.v[0] = s.x();
+                r.v[5] = s.y();
+                r.v[10] = s.z();
+                r
+        }
+
+        // fov_y is the full vertical field of view in radians, aspect is width / height
+        pub fn new_perspective(
+                fov_y: T,
+                aspect: T,
+                near: T,
+                far: T,
+                hand: Handedness,
+                depth: DepthRange,
+        ) -> Matrix4<T> {
+                let two = T::from_f64(2.0);
+                let f = T::one() / (fov_y / two).tan();
+                let d = hand.z_sign::<T>();
+                let mut r = Matrix4::zero();
+                r.v[0] = f / aspect;
+                r.v[5] = f;
+                match depth {
+                        DepthRange::NegOneToOne => {
+                                r.v[10] = d * (far + near) / (far - near);
+                                r.v[11] = -two * far * near / (far - near);
+                        }
+                        DepthRange::ZeroToOne => {
+                                r.v[10] = d * far / (far - near);
+                                r.v[11] = -far * near / (far - near);
+                        }
+                }
+                r.v[14] = d;
+                r
+        }
+
+        // perspective projection with the far plane pushed to infinity
+        pub fn new_perspective_infinite(
+                fov_y: T,
+                aspect: T,
+                near: T,
+                hand: Handedness,
+                depth: DepthRange,
+        ) -> Matrix4<T> {
+                let two = T::from_f64(2.0);
+                let f = T::one() / (fov_y / two).tan();
+                let d = hand.z_sign::<T>();
+                let mut r = Matrix4::zero();
+                r.v[0] = f / aspect;
+                r.v[5] = f;
+                r.v[10] = d;
+                r.v[11] = match depth {
+                        DepthRange::NegOneToOne => -two * near,
+                        DepthRange::ZeroToOne => -near,
+                };
+                r.v[14] = d;
+                r
+        }
+
+        #[allow(clippy::too_many_arguments)]
+        pub fn new_orthographic(
+                left: T,
+                right: T,
+                bottom: T,
+                top: T,
+                near: T,
+                far: T,
+                hand: Handedness,
+                depth: DepthRange,
+        ) -> Matrix4<T> {
+                let d = hand.z_sign::<T>();
+                let two = T::from_f64(2.0);
+                let mut r = Matrix4::identity();
+                r.v[0] = two / (right - left);
+                r.v[3] = -(right + left) / (right - left);
+                r.v[5] = two / (top - bottom);
+                r.v[7] = -(top + bottom) / (top - bottom);
+                match depth {
+                        DepthRange::NegOneToOne => {
+                                r.v[10] = d * two / (far - near);
+                                r.v[11] = -(far + near) / (far - near);
+                        }
+                        DepthRange::ZeroToOne => {
+                                r.v[10] = d / (far - near);
+                                r.v[11] = -near / (far - near);
+                        }
+                }
+                r
+        }
+
+        // view matrix placing the camera at eye, looking toward target
+        pub fn new_look_at(eye: Vector3<T>, target: Vector3<T>, up: Vector3<T>, hand: Handedness) -> Matrix4<T> {
+                let f = (target - eye).unitize();
+                let (s, u, z_axis) = match hand {
+                        Handedness::Right => {
+                                let s = f.cross(&up).unitize();
+                                (s, s.cross(&f), -f)
+                        }
+                        Handedness::Left => {
+                                let s = up.cross(&f).unitize();
+                                (s, f.cross(&s), f)
+                        }
+                };
+                let v = [
+                        s.x(), s.y(), s.z(), -s.dot(&eye),
+                        u.x(), u.y(), u.z(), -u.dot(&eye),
+                        z_axis.x(), z_axis.y(), z_axis.z(), -z_axis.dot(&eye),
+                        T::zero(), T::zero(), T::zero(), T::one(),
+                ];
+                Matrix4 { v }
+        }
+
+        // upper-left 3x3 block (rotation, scale and shear)
+        pub fn linear(&self) -> Matrix3<T> {
+                let v = [
+                        self.m00(), self.m01(), self.m02(),
+                        self.m10(), self.m11(), self.m12(),
+                        self.m20(), self.m21(), self.m22() ];
+                Matrix3 { v }
+        }
+
+        pub fn translation(&self) -> Vector3<T> {
+                Vector3::new(self.m03(), self.m13(), self.m23())
+        }
+
+        // treats p as (x, y, z, 1), divides by w when the last row is not (0, 0, 0, 1)
+        pub fn transform_point(&self, p: Vector3<T>) -> Vector3<T> {
+                let x = self.m00() * p.x() + self.m01() * p.y() + self.m02() * p.z() + self.m03();
+                let y = self.m10() * p.x() + self.m11() * p.y() + self.m12() * p.z() + self.m13();
+                let z = self.m20() * p.x() + self.m21() * p.y() + self.m22() * p.z() + self.m23();
+                let w = self.m30() * p.x() + self.m31() * p.y() + self.m32() * p.z() + self.m33();
+                if w == T::one() {
+                        Vector3::new(x, y, z)
+                } else {
+                        Vector3::new(x / w, y / w, z / w)
+                }
+        }
+
+        // treats d as (x, y, z, 0), translation does not apply
+        pub fn transform_vector(&self, d: Vector3<T>) -> Vector3<T> {
+                Vector3::new(
+                        self.m00() * d.x() + self.m01() * d.y() + self.m02() * d.z(),
+                        self.m10() * d.x() + self.m11() * d.y() + self.m12() * d.z(),
+                        self.m20() * d.x() + self.m21() * d.y() + self.m22() * d.z(),
+                )
+        }
+
+        pub fn transpose(&self) -> Matrix4<T> {
+                let v = [
+                        self.m00(), self.m10(), self.m20(), self.m30(),
+                        self.m01(), self.m11(), self.m21(), self.m31(),
+                        self.m02(), self.m12(), self.m22(), self.m32(),
+                        self.m03(), self.m13(), self.m23(), self.m33(),
+                ];
+                Matrix4 { v }
+        }
+
+        // 2x2 minors of the upper two rows (s) and the lower two rows (c),
+        // shared by the determinant and the adjugate (Laplace expansion).
+        fn minors(&self) -> ([T; 6], [T; 6]) {
+                let s = [
+                        self.m00() * self.m11() - self.m10() * self.m01(),
+                        self.m00() * self.m12() - self.m10() * self.m02(),
+                        self.m00() * self.m13() - self.m10() * self.m03(),
+                        self.m01() * self.m12() - self.m11() * self.m02(),
+                        self.m01() * self.m13() - self.m11() * self.m03(),
+                        self.m02() * self.m13() - self.m12() * self.m03(),
+                ];
+                let c = [
+                        self.m20() * self.m31() - self.m30() * self.m21(),
+                        self.m20() * self.m32() - self.m30() * self.m22(),
+                        self.m20() * self.m33() - self.m30() * self.m23(),
+                        self.m21() * self.m32() - self.m31() * self.m22(),
+                        self.m21() * self.m33() - self.m31() * self.m23(),
+                        self.m22() * self.m33() - self.m32() * self.m23(),
+                ];
+                (s, c)
+        }
+
+        pub fn determinant(&self) -> T {
+                let (s, c) = self.minors();
+                s[0] * c[5] - s[1] * c[4] + s[2] * c[3] + s[3] * c[2] - s[4] * c[1] + s[5] * c[0]
+        }
+
+        pub fn adjugate(&self) -> Matrix4<T> {
+                let (s, c) = self.minors();
+                let v = [
+                         self.m11() * c[5] - self.m12() * c[4] + self.m13() * c[3],
+                        -self.m01() * c[5] + self.m02() * c[4] - self.m03() * c[3],
+                         self.m31() * s[5] - self.m32() * s[4] + self.m33() * s[3],
+                        -self.m21() * s[5] + self.m22() * s[4] - self.m23() * s[3],
+
+                        -self.m10() * c[5] + self.m12() * c[2] - self.m13() * c[1],
+                         self.m00() * c[5] - self.m02() * c[2] + self.m03() * c[1],
+                        -self.m30() * s[5] + self.m32() * s[2] - self.m33() * s[1],
+                         self.m20() * s[5] - self.m22() * s[2] + self.m23() * s[1],
+
+                         self.m10() * c[4] - self.m11() * c[2] + self.m13() * c[0],
+                        -self.m00() * c[4] + self.m01() * c[2] - self.m03() * c[0],
+                         self.m30() * s[4] - self.m31() * s[2] + self.m33() * s[0],
+                        -self.m20() * s[4] + self.m21() * s[2] - self.m23() * s[0],
+
+                        -self.m10() * c[3] + self.m11() * c[1] - self.m12() * c[0],
+                         self.m00() * c[3] - self.m01() * c[1] + self.m02() * c[0],
+                        -self.m30() * s[3] + self.m31() * s[1] - self.m32() * s[0],
+                         self.m20() * s[3] - self.m21() * s[1] + self.m22() * s[0],
+                ];
+                Matrix4 { v }
+        }
+
+        // returns None when the matrix is singular (or too close to it),
+        // see Matrix::negligible_determinant
+        pub fn try_inverse(&self) -> Option<Matrix4<T>> {
+                let det = self.determinant();
+                if Matrix::from(*self).negligible_determinant(det) {
+                        return None;
+                }
+                let mut r = self.adjugate();
+                for e in r.v.iter_mut() {
+                        *e /= det;
+                }
+                Some(r)
         }
 
         //FIXME: impl for fmt
@@ -140,52 +429,52 @@ impl Mat4 {
                 );
         }
 
-        pub fn m00(&self) -> f64 {
+        pub fn m00(&self) -> T {
                 self.v[0]
         }
-        pub fn m01(&self) -> f64 {
+        pub fn m01(&self) -> T {
                 self.v[1]
         }
-        pub fn m02(&self) -> f64 {
+        pub fn m02(&self) -> T {
                 self.v[2]
         }
-        pub fn m03(&self) -> f64 {
+        pub fn m03(&self) -> T {
                 self.v[3]
         }
-        pub fn m10(&self) -> f64 {
+        pub fn m10(&self) -> T {
                 self.v[4]
         }
-        pub fn m11(&self) -> f64 {
+        pub fn m11(&self) -> T {
                 self.v[5]
         }
-        pub fn m12(&self) -> f64 {
+        pub fn m12(&self) -> T {
                 self.v[6]
         }
-        pub fn m13(&self) -> f64 {
+        pub fn m13(&self) -> T {
                 self.v[7]
         }
-        pub fn m20(&self) -> f64 {
+        pub fn m20(&self) -> T {
                 self.v[8]
         }
-        pub fn m21(&self) -> f64 {
+        pub fn m21(&self) -> T {
                 self.v[9]
         }
-        pub fn m22(&self) -> f64 {
+        pub fn m22(&self) -> T {
                 self.v[10]
         }
-        pub fn m23(&self) -> f64 {
+        pub fn m23(&self) -> T {
                 self.v[11]
         }
-        pub fn m30(&self) -> f64 {
+        pub fn m30(&self) -> T {
                 self.v[12]
         }
-        pub fn m31(&self) -> f64 {
+        pub fn m31(&self) -> T {
                 self.v[13]
         }
-        pub fn m32(&self) -> f64 {
+        pub fn m32(&self) -> T {
                 self.v[14]
         }
-        pub fn m33(&self) -> f64 {
+        pub fn m33(&self) -> T {
                 self.v[15]
         }
 }
@@ -0,0 +1,339 @@
+use std::ops::{Add, Index, IndexMut, Mul, Neg, Sub};
+use std::convert::From;
+use super::matrix3::Matrix3;
+use super::matrix4::Matrix4;
+use super::scalar::Float;
+use super::vector::Vector3;
+use super::vector_n::VectorN;
+
+// R x C matrix, v[row][col], acting on column vectors like Matrix3 and Matrix4.
+// Matrix3 and Matrix4 keep their flat layout and convert to and from the
+// square sizes; their products go through the generic ones here.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Matrix<T, const R: usize, const C: usize> {
+        pub v: [[T; C]; R],
+}
+
+pub type Mat<const R: usize, const C: usize> = Matrix<f64, R, C>;
+pub type Matf<const R: usize, const C: usize> = Matrix<f32, R, C>;
+// 6x6 operators on spatial vectors, e.g. spatial inertia
+pub type Mat6 = Mat<6, 6>;
+pub type Mat6f = Matf<6, 6>;
+
+impl<T: Float, const R: usize, const K: usize, const C: usize> Mul<Matrix<T, K, C>> for Matrix<T, R, K> {
+        type Output = Matrix<T, R, C>;
+
+        fn mul(self, other: Matrix<T, K, C>) -> Matrix<T, R, C> {
+                let mut r = Matrix::zero();
+                for (row, a) in r.v.iter_mut().zip(self.v.iter()) {
+                        for (c, e) in row.iter_mut().enumerate() {
+                                *e = a.iter().zip(other.v.iter()).fold(T::zero(), |s, (x, b)| s + *x * b[c]);
+                        }
+                }
+                r
+        }
+}
+
+impl<T: Float, const R: usize, const C: usize> Mul<VectorN<T, C>> for Matrix<T, R, C> {
+        type Output = VectorN<T, R>;
+
+        fn mul(self, o: VectorN<T, C>) -> VectorN<T, R> {
+                let mut r = VectorN::zero();
+                for (e, row) in r.0.iter_mut().zip(self.v.iter()) {
+                        *e = VectorN(*row).dot(&o);
+                }
+                r
+        }
+}
+
+impl<T: Float, const R: usize, const C: usize> Mul<T> for Matrix<T, R, C> {
+        type Output = Matrix<T, R, C>;
+
+        fn mul(self, s: T) -> Matrix<T, R, C> {
+                let mut r = self;
+                for e in r.v.iter_mut().flat_map(|row| row.iter_mut()) {
+                        *e *= s;
+                }
+                r
+        }
+}
+
+impl<T: Float, const R: usize, const C: usize> Add for Matrix<T, R, C> {
+        type Output = Matrix<T, R, C>;
+
+        fn add(self, other: Matrix<T, R, C>) -> Matrix<T, R, C> {
+                let mut r = self;
+                for (e, o) in r.v.iter_mut().flat_map(|row| row.iter_mut()).zip(other.v.iter().flat_map(|row| row.iter())) {
+                        *e += *o;
+                }
+                r
+        }
+}
+
+impl<T: Float, const R: usize, const C: usize> Sub for Matrix<T, R, C> {
+        type Output = Matrix<T, R, C>;
+
+        fn sub(self, other: Matrix<T, R, C>) -> Matrix<T, R, C> {
+                self + -other
+        }
+}
+
+impl<T: Float, const R: usize, const C: usize> Neg for Matrix<T, R, C> {
+        type Output = Matrix<T, R, C>;
+
+        fn neg(self) -> Matrix<T, R, C> {
+                self * -T::one()
+        }
+}
+
+// m[(row, col)]
+impl<T: Float, const R: usize, const C: usize> Index<(usize, usize)> for Matrix<T, R, C> {
+        type Output = T;
+
+        fn index(&self, (r, c): (usize, usize)) -> &T {
+                &self.v[r][c]
+        }
+}
+
+impl<T: Float, const R: usize, const C: usize> IndexMut<(usize, usize)> for Matrix<T, R, C> {
+        fn index_mut(&mut self, (r, c): (usize, usize)) -> &mut T {
+                &mut self.v[r][c]
+        }
+}
+
+impl<const R: usize, const C: usize> From<Matrix<f32, R, C>> for Matrix<f64, R, C> {
+        fn from(o: Matrix<f32, R, C>) -> Matrix<f64, R, C> {
+                o.cast()
+        }
+}
+
+impl<T: Float> From<Matrix3<T>> for Matrix<T, 3, 3> {
+        fn from(m: Matrix3<T>) -> Matrix<T, 3, 3> {
+                let mut r = Matrix::zero();
+                for (i, e) in m.v.iter().enumerate() {
+                        r.v[i / 3][i % 3] = *e;
+                }
+                r
+        }
+}
+
+impl<T: Float> From<Matrix<T, 3, 3>> for Matrix3<T> {
+        fn from(m: Matrix<T, 3, 3>) -> Matrix3<T> {
+                let mut r = Matrix3::zero();
+                for (i, e) in r.v.iter_mut().enumerate() {
+                        *e = m.v[i / 3][i % 3];
+                }
+                r
+        }
+}
+
+impl<T: Float> From<Matrix4<T>> for Matrix<T, 4, 4> {
+        fn from(m: Matrix4<T>) -> Matrix<T, 4, 4> {
+                let mut r = Matrix::zero();
+                for (i, e) in m.v.iter().enumerate() {
+                        r.v[i / 4][i % 4] = *e;
+                }
+                r
+        }
+}
+
+impl<T: Float> From<Matrix<T, 4, 4>> for Matrix4<T> {
+        fn from(m: Matrix<T, 4, 4>) -> Matrix4<T> {
+                let mut r = Matrix4::zero();
+                for (i, e) in r.v.iter_mut().enumerate() {
+                        *e = m.v[i / 4][i % 4];
+                }
+                r
+        }
+}
+
+impl<T: Float, const R: usize, const C: usize> Matrix<T, R, C> {
+        pub fn new(v: [[T; C]; R]) -> Matrix<T, R, C> {
+                Matrix { v }
+        }
+
+        pub fn zero() -> Matrix<T, R, C> {
+                Matrix { v: [[T::zero(); C]; R] }
+        }
+
+        pub fn new_from_cols(cols: [VectorN<T, R>; C]) -> Matrix<T, R, C> {
+                let mut m = Matrix::zero();
+                for (c, col) in cols.iter().enumerate() {
+                        for (row, e) in m.v.iter_mut().zip(col.0.iter()) {
+                                row[c] = *e;
+                        }
+                }
+                m
+        }
+
+        pub fn cast<U: Float>(&self) -> Matrix<U, R, C> {
+                let mut r = Matrix::zero();
+                for (a, b) in r.v.iter_mut().zip(self.v.iter()) {
+                        for (e, o) in a.iter_mut().zip(b.iter()) {
+                                *e = U::from_f64(o.to_f64());
+                        }
+                }
+                r
+        }
+
+        pub fn row(&self, i: usize) -> VectorN<T, C> {
+                VectorN(self.v[i])
+        }
+
+        pub fn col(&self, j: usize) -> VectorN<T, R> {
+                let mut r = VectorN::zero();
+                for (e, row) in r.0.iter_mut().zip(self.v.iter()) {
+                        *e = row[j];
+                }
+                r
+        }
+
+        pub fn transpose(&self) -> Matrix<T, C, R> {
+                let mut t = Matrix::zero();
+                for (r, row) in self.v.iter().enumerate() {
+                        for (c, e) in row.iter().enumerate() {
+                                t.v[c][r] = *e;
+                        }
+                }
+                t
+        }
+}
+
+#[allow(clippy::needless_range_loop)]
+impl<T: Float, const N: usize> Matrix<T, N, N> {
+        pub fn identity() -> Matrix<T, N, N> {
+                let mut m = Matrix::zero();
+                for i in 0..N {
+                        m.v[i][i] = T::one();
+                }
+                m
+        }
+
+        pub fn trace(&self) -> T {
+                (0..N).fold(T::zero(), |s, i| s + self.v[i][i])
+        }
+
+        // true when det is negligible next to the product of the row lengths, the largest
+        // value the determinant can take (hadamard), so the test does not depend on the
+        // scale of the entries. try_inverse of Matrix3 and Matrix4 rejects with it.
+        pub(crate) fn negligible_determinant(&self, det: T) -> bool {
+                let bound = self.v.iter().fold(T::one(), |p, r| p * r.iter().fold(T::zero(), |s, e| s + *e * *e).sqrt());
+                det.abs() <= T::epsilon() * bound
+        }
+
+        // partial pivoting lu in place: returns the packed factors (l below the diagonal,
+        // u on and above it), the row permutation and its sign. A column without a
+        // nonzero pivot is skipped, leaving a zero on the diagonal of u.
+        fn lu_packed(&self) -> (Matrix<T, N, N>, [usize; N], T) {
+                let mut a = *self;
+                let mut perm = [0usize; N];
+                for (i, p) in perm.iter_mut().enumerate() {
+                        *p = i;
+                }
+                let mut sign = T::one();
+                for c in 0..N {
+                        let p = (c..N).fold(c, |p, r| if a.v[r][c].abs() > a.v[p][c].abs() { r } else { p });
+                        if p != c {
+                                a.v.swap(p, c);
+                                perm.swap(p, c);
+                                sign = -sign;
+                        }
+                        if a.v[c][c] == T::zero() {
+                                continue;
+                        }
+                        for r in (c + 1)..N {
+                                let f = a.v[r][c] / a.v[c][c];
+                                a.v[r][c] = f;
+                                for k in (c + 1)..N {
+                                        let x = a.v[c][k];
+                                        a.v[r][k] -= f * x;
+                                }
+                        }
+                }
+                (a, perm, sign)
+        }
+
+        // gaussian elimination with partial pivoting, returns (p, l, u) with
+        // p * self = l * u, l unit lower triangular and u upper triangular.
+        // a singular matrix leaves zeros on the diagonal of u.
+        pub fn lu(&self) -> (Matrix<T, N, N>, Matrix<T, N, N>, Matrix<T, N, N>) {
+                let (a, perm, _) = self.lu_packed();
+                let mut p = Matrix::zero();
+                let mut l = Matrix::identity();
+                let mut u = Matrix::zero();
+                for r in 0..N {
+                        p.v[r][perm[r]] = T::one();
+                        for c in 0..N {
+                                if c < r {
+                                        l.v[r][c] = a.v[r][c];
+                                } else {
+                                        u.v[r][c] = a.v[r][c];
+                                }
+                        }
+                }
+                (p, l, u)
+        }
+
+        pub fn determinant(&self) -> T {
+                let (a, _, sign) = self.lu_packed();
+                (0..N).fold(sign, |d, i| d * a.v[i][i])
+        }
+
+        // x with self * x = b, None when self is singular.
+        // singular means a pivot that vanishes against the largest entry
+        pub fn solve(&self, b: VectorN<T, N>) -> Option<VectorN<T, N>> {
+                let (a, perm, _) = self.lu_packed();
+                let scale = self.v.iter().flat_map(|r| r.iter()).fold(T::zero(), |m, e| m.max(e.abs()));
+                if (0..N).any(|i| a.v[i][i].abs() <= T::epsilon() * scale) {
+                        return None;
+                }
+                let mut x = VectorN::zero();
+                for (e, p) in x.0.iter_mut().zip(perm.iter()) {
+                        *e = b.0[*p];
+                }
+                for r in 0..N {
+                        for k in 0..r {
+                                let y = x.0[k];
+                                x.0[r] -= a.v[r][k] * y;
+                        }
+                }
+                for r in (0..N).rev() {
+                        for k in (r + 1)..N {
+                                let y = x.0[k];
+                                x.0[r] -= a.v[r][k] * y;
+                        }
+                        x.0[r] /= a.v[r][r];
+                }
+                Some(x)
+        }
+
+        pub fn try_inverse(&self) -> Option<Matrix<T, N, N>> {
+                let mut cols = [VectorN::zero(); N];
+                for (i, c) in cols.iter_mut().enumerate() {
+                        *c = self.solve(VectorN::unit(i))?;
+                }
+                Some(Matrix::new_from_cols(cols))
+        }
+}
+
+impl<T: Float> Matrix<T, 6, 6> {
+        // spatial inertia of a rigid body about the body origin, acting on twists
+        // (linear velocity, angular velocity) and giving (linear, angular) momentum.
+        // com is the centre of mass and inertia the rotational inertia about it.
+        pub fn new_spatial_inertia(mass: T, com: Vector3<T>, inertia: Matrix3<T>) -> Matrix<T, 6, 6> {
+                let zero = T::zero();
+                let hat = Matrix3 { v: [
+                        zero, -com.z(), com.y(),
+                        com.z(), zero, -com.x(),
+                        -com.y(), com.x(), zero ] };
+                let c = hat * mass;
+                let blocks = [[Matrix3::identity() * mass, -c], [c, inertia - hat * c]];
+                let mut m = Matrix::zero();
+                for (r, row) in m.v.iter_mut().enumerate() {
+                        for (col, e) in row.iter_mut().enumerate() {
+                                *e = blocks[r / 3][col / 3][(r % 3, col % 3)];
+                        }
+                }
+                m
+        }
+}
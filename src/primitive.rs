@@ -0,0 +1,246 @@
+use super::scalar::Float;
+use super::vector::Vector3;
+
+// half line origin + t * direction with t >= 0. direction need not be unit length,
+// hit parameters are in multiples of it.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Ray<T> {
+        pub origin: Vector3<T>,
+        pub direction: Vector3<T>,
+}
+
+// infinite line point + t * direction
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Line<T> {
+        pub point: Vector3<T>,
+        pub direction: Vector3<T>,
+}
+
+// start + t * (end - start) with t in [0, 1]
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Segment<T> {
+        pub start: Vector3<T>,
+        pub end: Vector3<T>,
+}
+
+// points x with normal . x = d, normal is unit length
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Plane<T> {
+        pub normal: Vector3<T>,
+        pub d: T,
+}
+
+// where a ray meets a surface: the ray parameter, the point and the surface normal there
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct RayHit<T> {
+        pub t: T,
+        pub point: Vector3<T>,
+        pub normal: Vector3<T>,
+}
+
+// where three planes meet. determinant is the triple product of their unit normals:
+// 1 for perpendicular planes, shrinking towards 0 as the planes approach a common line
+// and the point grows sensitive to small changes in them
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct PlanesHit<T> {
+        pub point: Vector3<T>,
+        pub determinant: T,
+}
+
+// closest points between two lines or segments, point_a = a.at(s) and point_b = b.at(t)
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct ClosestPoints<T> {
+        pub s: T,
+        pub t: T,
+        pub point_a: Vector3<T>,
+        pub point_b: Vector3<T>,
+        pub distance: T,
+}
+
+fn clamp01<T: Float>(x: T) -> T {
+        x.max(T::zero()).min(T::one())
+}
+
+fn closest_points<T: Float>(a: Vector3<T>, s: T, b: Vector3<T>, t: T) -> ClosestPoints<T> {
+        ClosestPoints { s, t, point_a: a, point_b: b, distance: a.distance(b) }
+}
+
+impl<T: Float> Ray<T> {
+        pub fn new(origin: Vector3<T>, direction: Vector3<T>) -> Ray<T> {
+                Ray { origin, direction }
+        }
+
+        pub fn at(&self, t: T) -> Vector3<T> {
+                self.origin + self.direction.scalar_mul(t)
+        }
+
+        // None when the ray runs parallel to the plane or points away from it.
+        // the normal of the hit is the plane normal, whichever side the ray comes from.
+        pub fn intersect_plane(&self, plane: &Plane<T>) -> Option<RayHit<T>> {
+                let denom = plane.normal.dot(&self.direction);
+                if denom.abs() <= T::epsilon() * self.direction.length() {
+                        return None;
+                }
+                let t = (plane.d - plane.normal.dot(&self.origin)) / denom;
+                if t < T::zero() {
+                        return None;
+                }
+                Some(RayHit { t, point: self.at(t), normal: plane.normal })
+        }
+}
+
+impl<T: Float> Line<T> {
+        pub fn new(point: Vector3<T>, direction: Vector3<T>) -> Line<T> {
+                Line { point, direction }
+        }
+
+        pub fn new_through(a: Vector3<T>, b: Vector3<T>) -> Line<T> {
+                Line { point: a, direction: b - a }
+        }
+
+        pub fn at(&self, t: T) -> Vector3<T> {
+                self.point + self.direction.scalar_mul(t)
+        }
+
+        // parameter and point on the line closest to p
+        pub fn closest_point(&self, p: Vector3<T>) -> (T, Vector3<T>) {
+                let t = (p - self.point).dot(&self.direction) / self.direction.dot(&self.direction);
+                (t, self.at(t))
+        }
+
+        // for parallel lines every point pairs up with one on the other line;
+        // s = 0 is picked then
+        pub fn closest_points(&self, other: &Line<T>) -> ClosestPoints<T> {
+                let r = self.point - other.point;
+                let a = self.direction.dot(&self.direction);
+                let b = self.direction.dot(&other.direction);
+                let c = self.direction.dot(&r);
+                let e = other.direction.dot(&other.direction);
+                let f = other.direction.dot(&r);
+                let denom = a * e - b * b;
+                let (s, t) = if denom <= T::epsilon() * a * e {
+                        (T::zero(), f / e)
+                } else {
+                        ((b * f - c * e) / denom, (a * f - b * c) / denom)
+                };
+                closest_points(self.at(s), s, other.at(t), t)
+        }
+}
+
+impl<T: Float> Segment<T> {
+        pub fn new(start: Vector3<T>, end: Vector3<T>) -> Segment<T> {
+                Segment { start, end }
+        }
+
+        pub fn at(&self, t: T) -> Vector3<T> {
+                self.start + (self.end - self.start).scalar_mul(t)
+        }
+
+        pub fn direction(&self) -> Vector3<T> {
+                self.end - self.start
+        }
+
+        pub fn length(&self) -> T {
+                self.direction().length()
+        }
+
+        pub fn closest_point(&self, p: Vector3<T>) -> (T, Vector3<T>) {
+                let d = self.direction();
+                let dd = d.dot(&d);
+                let t = if dd == T::zero() { T::zero() } else { clamp01((p - self.start).dot(&d) / dd) };
+                (t, self.at(t))
+        }
+
+        // Ericson, real-time collision detection 5.1.9. Degenerate segments are points.
+        pub fn closest_points(&self, other: &Segment<T>) -> ClosestPoints<T> {
+                let d1 = self.direction();
+                let d2 = other.direction();
+                let r = self.start - other.start;
+                let a = d1.dot(&d1);
+                let e = d2.dot(&d2);
+                let f = d2.dot(&r);
+                let eps = T::epsilon();
+
+                let (s, t) = if a <= eps && e <= eps {
+                        (T::zero(), T::zero())
+                } else if a <= eps {
+                        (T::zero(), clamp01(f / e))
+                } else {
+                        let c = d1.dot(&r);
+                        if e <= eps {
+                                (clamp01(-c / a), T::zero())
+                        } else {
+                                let b = d1.dot(&d2);
+                                let denom = a * e - b * b;
+                                let s = if denom > eps * a * e { clamp01((b * f - c * e) / denom) } else { T::zero() };
+                                let t = (b * s + f) / e;
+                                if t < T::zero() {
+                                        (clamp01(-c / a), T::zero())
+                                } else if t > T::one() {
+                                        (clamp01((b - c) / a), T::one())
+                                } else {
+                                        (s, t)
+                                }
+                        }
+                };
+                closest_points(self.at(s), s, other.at(t), t)
+        }
+
+        pub fn distance(&self, other: &Segment<T>) -> T {
+                self.closest_points(other).distance
+        }
+}
+
+impl<T: Float> Plane<T> {
+        // normal is unitized, None when it has no direction (zero length)
+        pub fn new(normal: Vector3<T>, d: T) -> Option<Plane<T>> {
+                let length = normal.length();
+                if length.is_nan() || length <= T::zero() {
+                        return None;
+                }
+                Some(Plane { normal: normal.scalar_div(length), d: d / length })
+        }
+
+        pub fn new_from_point_normal(point: Vector3<T>, normal: Vector3<T>) -> Option<Plane<T>> {
+                let plane = Plane::new(normal, T::zero())?;
+                Some(Plane { d: plane.normal.dot(&point), ..plane })
+        }
+
+        // counter-clockwise a, b, c seen from the side the normal points to.
+        // None when the points are collinear.
+        pub fn new_from_three_points(a: Vector3<T>, b: Vector3<T>, c: Vector3<T>) -> Option<Plane<T>> {
+                let normal = Vector3::normal_from_three_vertices(a, b, c);
+                if normal.x().is_nan() || normal.y().is_nan() || normal.z().is_nan() {
+                        return None;
+                }
+                Some(Plane { normal, d: normal.dot(&a) })
+        }
+
+        // positive on the side the normal points to
+        pub fn signed_distance(&self, p: Vector3<T>) -> T {
+                self.normal.dot(&p) - self.d
+        }
+
+        pub fn project_point(&self, p: Vector3<T>) -> Vector3<T> {
+                p - self.normal.scalar_mul(self.signed_distance(p))
+        }
+
+        // the point of the plane closest to the origin
+        pub fn point(&self) -> Vector3<T> {
+                self.normal.scalar_mul(self.d)
+        }
+
+        // the single common point, None when two of the planes are parallel
+        // or all three share a line
+        pub fn intersect_three(a: &Plane<T>, b: &Plane<T>, c: &Plane<T>) -> Option<PlanesHit<T>> {
+                let bc = b.normal.cross(&c.normal);
+                let denom = a.normal.dot(&bc);
+                if denom.abs() <= T::epsilon().sqrt() {
+                        return None;
+                }
+                let p = bc.scalar_mul(a.d)
+                        + c.normal.cross(&a.normal).scalar_mul(b.d)
+                        + a.normal.cross(&b.normal).scalar_mul(c.d);
+                Some(PlanesHit { point: p.scalar_div(denom), determinant: denom })
+        }
+}
@@ -1,20 +1,87 @@
-use super::vector::{Angle, Vec3};
+use super::scalar::Float;
+use super::vector::Vector3;
+use super::matrix3::Matrix3;
+use super::matrix4::Matrix4;
 use std::convert::From;
-use std::ops::{Add, Mul};
+use std::ops::{Add, AddAssign, Div, Mul, MulAssign, Neg, Sub, SubAssign};
 
 #[derive(Debug, Copy, Clone, PartialEq)]
-pub struct Quat(f64, f64, f64, f64);
+pub struct Quaternion<T>(T, T, T, T);
 
-impl From<Vec3> for Quat {
-  fn from(original: Vec3) -> Quat {
-    Quat(0.0, original.x(), original.y(), original.z())
+pub type Quat = Quaternion<f64>;
+pub type Quatf = Quaternion<f32>;
+
+impl<T: Float> From<Vector3<T>> for Quaternion<T> {
+  fn from(original: Vector3<T>) -> Quaternion<T> {
+    Quaternion(T::zero(), original.x(), original.y(), original.z())
   }
 }
 
-impl Add for Quat {
-  type Output = Quat;
-  fn add(self, other: Quat) -> Quat {
-    Quat(
+// Shepperd's method: pick the largest of w, x, y, z from the trace and the diagonal
+// first so the square root and the division never work on a small number.
+// m should be a proper rotation, the result is a unit quaternion with w >= 0.
+impl<T: Float> From<Matrix3<T>> for Quaternion<T> {
+  fn from(m: Matrix3<T>) -> Quaternion<T> {
+    let one = T::one();
+    let quarter = T::from_f64(0.25);
+    let trace = m.trace();
+
+    let q = if trace >= m.m00() && trace >= m.m11() && trace >= m.m22() {
+      let s = (one + trace).sqrt() * T::from_f64(2.0); // 4w
+      Quaternion(
+        quarter * s,
+        (m.m21() - m.m12()) / s,
+        (m.m02() - m.m20()) / s,
+        (m.m10() - m.m01()) / s,
+      )
+    } else if m.m00() >= m.m11() && m.m00() >= m.m22() {
+      let s = (one + m.m00() - m.m11() - m.m22()).sqrt() * T::from_f64(2.0); // 4x
+      Quaternion(
+        (m.m21() - m.m12()) / s,
+        quarter * s,
+        (m.m01() + m.m10()) / s,
+        (m.m02() + m.m20()) / s,
+      )
+    } else if m.m11() >= m.m22() {
+      let s = (one + m.m11() - m.m00() - m.m22()).sqrt() * T::from_f64(2.0); // 4y
+      Quaternion(
+        (m.m02() - m.m20()) / s,
+        (m.m01() + m.m10()) / s,
+        quarter * s,
+        (m.m12() + m.m21()) / s,
+      )
+    } else {
+      let s = (one + m.m22() - m.m00() - m.m11()).sqrt() * T::from_f64(2.0); // 4z
+      Quaternion(
+        (m.m10() - m.m01()) / s,
+        (m.m02() + m.m20()) / s,
+        (m.m12() + m.m21()) / s,
+        quarter * s,
+      )
+    };
+
+    let q = q.unitize();
+    if q.w() < T::zero() { -q } else { q }
+  }
+}
+
+// rotation part of an affine matrix, the upper-left block must not carry scale or shear
+impl<T: Float> From<Matrix4<T>> for Quaternion<T> {
+  fn from(m: Matrix4<T>) -> Quaternion<T> {
+    Quaternion::from(m.linear())
+  }
+}
+
+impl From<Quaternion<f32>> for Quaternion<f64> {
+  fn from(o: Quaternion<f32>) -> Quaternion<f64> {
+    o.cast()
+  }
+}
+
+impl<T: Float> Add for Quaternion<T> {
+  type Output = Quaternion<T>;
+  fn add(self, other: Quaternion<T>) -> Quaternion<T> {
+    Quaternion(
       self.0 + other.w(),
       self.1 + other.x(),
       self.2 + other.y(),
@@ -23,11 +90,11 @@ impl Add for Quat {
   }
 }
 
-impl Mul for Quat {
-  type Output = Quat;
+impl<T: Float> Mul for Quaternion<T> {
+  type Output = Quaternion<T>;
 
-  fn mul(self, other: Quat) -> Quat {
-    Quat(
+  fn mul(self, other: Quaternion<T>) -> Quaternion<T> {
+    Quaternion(
       self.w() * other.w() - self.x() * other.x() - self.y() * other.y() - self.z() * other.z(),
       self.w() * other.x() + self.x() * other.w() + self.y() * other.z() - self.z() * other.y(),
       self.w() * other.y() + self.y() * other.w() + self.z() * other.x() - self.x() * other.z(),
@@ -36,56 +103,130 @@ impl Mul for Quat {
   }
 }
 
-impl Quat {
+impl<T: Float> Sub for Quaternion<T> {
+  type Output = Quaternion<T>;
+  fn sub(self, other: Quaternion<T>) -> Quaternion<T> {
+    Quaternion(
+      self.0 - other.w(),
+      self.1 - other.x(),
+      self.2 - other.y(),
+      self.3 - other.z(),
+    )
+  }
+}
+
+impl<T: Float> Neg for Quaternion<T> {
+  type Output = Quaternion<T>;
+  fn neg(self) -> Quaternion<T> {
+    Quaternion(-self.0, -self.1, -self.2, -self.3)
+  }
+}
+
+impl<T: Float> Mul<T> for Quaternion<T> {
+  type Output = Quaternion<T>;
+  fn mul(self, s: T) -> Quaternion<T> {
+    self.scalar_mul(s)
+  }
+}
+
+impl<T: Float> Div<T> for Quaternion<T> {
+  type Output = Quaternion<T>;
+  fn div(self, s: T) -> Quaternion<T> {
+    Quaternion(self.0 / s, self.1 / s, self.2 / s, self.3 / s)
+  }
+}
+
+// q * v rotates v, same as rotate_vec3
+impl<T: Float> Mul<Vector3<T>> for Quaternion<T> {
+  type Output = Vector3<T>;
+  fn mul(self, v: Vector3<T>) -> Vector3<T> {
+    self.rotate_vec3(v)
+  }
+}
+
+impl<T: Float> AddAssign for Quaternion<T> {
+  fn add_assign(&mut self, other: Quaternion<T>) {
+    *self = *self + other;
+  }
+}
+
+impl<T: Float> SubAssign for Quaternion<T> {
+  fn sub_assign(&mut self, other: Quaternion<T>) {
+    *self = *self - other;
+  }
+}
+
+impl<T: Float> MulAssign for Quaternion<T> {
+  fn mul_assign(&mut self, other: Quaternion<T>) {
+    *self = *self * other;
+  }
+}
+
+impl<T: Float> Quaternion<T> {
   #[allow(dead_code)]
-  pub fn zero() -> Quat {
-    Quat(0.0, 0.0, 0.0, 0.0)
+  pub fn zero() -> Quaternion<T> {
+    Quaternion(T::zero(), T::zero(), T::zero(), T::zero())
   }
 
-  pub fn new(w: f64, x: f64, y: f64, z: f64) -> Quat {
-    Quat(w, x, y, z)
+  pub fn new(w: T, x: T, y: T, z: T) -> Quaternion<T> {
+    Quaternion(w, x, y, z)
   }
 
-  pub fn new_from_angle_axis(rad: f64, axis: Vec3) -> Quat {
+  pub fn new_from_angle_axis(rad: T, axis: Vector3<T>) -> Quaternion<T> {
     let axis = axis.unitize();
-    let sin_halve_theta = (rad * 0.5).sin();
+    let half = T::from_f64(0.5);
+    let sin_halve_theta = (rad * half).sin();
 
-    Quat(
-      (rad * 0.5).cos(),
+    Quaternion(
+      (rad * half).cos(),
       axis.x() * sin_halve_theta,
       axis.y() * sin_halve_theta,
       axis.z() * sin_halve_theta,
     )
   }
 
-  pub fn new_from_vec(w: f64, v: Vec3) -> Quat {
-    Quat::new(w, v.x(), v.y(), v.z())
+  pub fn new_from_vec(w: T, v: Vector3<T>) -> Quaternion<T> {
+    Quaternion::new(w, v.x(), v.y(), v.z())
   }
 
-  pub fn w(&self) -> f64 {
+  // converts between precisions, rounding when narrowing to f32
+  pub fn cast<U: Float>(&self) -> Quaternion<U> {
+    Quaternion(
+      U::from_f64(self.0.to_f64()),
+      U::from_f64(self.1.to_f64()),
+      U::from_f64(self.2.to_f64()),
+      U::from_f64(self.3.to_f64()),
+    )
+  }
+
+  pub fn w(&self) -> T {
     self.0
   }
-  pub fn x(&self) -> f64 {
+  pub fn x(&self) -> T {
     self.1
   }
-  pub fn y(&self) -> f64 {
+  pub fn y(&self) -> T {
     self.2
   }
-  pub fn z(&self) -> f64 {
+  pub fn z(&self) -> T {
     self.3
   }
 
-  pub fn length(&self) -> f64 {
+  pub fn dot(&self, other: &Quaternion<T>) -> T {
+    self.0 * other.0 + self.1 * other.1 + self.2 * other.2 + self.3 * other.3
+  }
+
+  pub fn length(&self) -> T {
     (self.0.powi(2) + self.1.powi(2) + self.2.powi(2) + self.3.powi(2)).sqrt()
   }
 
-  pub fn scalar_mul(&self, s: f64) -> Quat {
-    Quat(self.0 * s, self.1 * s, self.2 * s, self.3 * s)
+  pub fn scalar_mul(&self, s: T) -> Quaternion<T> {
+    Quaternion(self.0 * s, self.1 * s, self.2 * s, self.3 * s)
   }
 
-  pub fn unitize(&self) -> Quat {
+  pub fn unitize(&self) -> Quaternion<T> {
     let length = self.length();
-    Quat(
+    Quaternion(
       self.0 / length,
       self.1 / length,
       self.2 / length,
@@ -93,53 +234,208 @@ impl Quat {
     )
   }
 
-  pub fn conjugate(&self) -> Quat {
+  pub fn conjugate(&self) -> Quaternion<T> {
     // let v = Vec3::from(self).scalar_mul(-1.0);
-    Quat(self.0, -self.1, -self.2, -self.3)
+    Quaternion(self.0, -self.1, -self.2, -self.3)
+  }
+
+  pub fn inverse(&self) -> Quaternion<T> {
+    let n = self.dot(self);
+    Quaternion(self.0 / n, -self.1 / n, -self.2 / n, -self.3 / n)
   }
 
-  pub fn rotate_vec3(self, v: Vec3) -> Vec3 {
-    Vec3::from(self * Quat::from(v) * self.conjugate())
+  pub fn rotate_vec3(self, v: Vector3<T>) -> Vector3<T> {
+    Vector3::from(self * Quaternion::from(v) * self.conjugate())
   }
 
-  pub fn angle_axis(self) -> (f64, Vec3) {
+  // angle in [0, 2pi] and a unit axis. The identity has no defined axis,
+  // it returns a zero angle about unit_x instead of dividing by sin(0).
+  pub fn angle_axis(self) -> (T, Vector3<T>) {
     let u = self.unitize();
 
-    let angle = u.w().acos() * 2.0;
+    let v = Vector3::from(u);
+    let sin_halve_theta = v.length();
+
+    if sin_halve_theta < T::epsilon() {
+      return (T::zero(), Vector3::unit_x());
+    }
+
+    let angle = sin_halve_theta.atan2(u.w()) * T::from_f64(2.0);
+
+    (angle, v.scalar_div(sin_halve_theta))
+  }
+
+  // rotation vector (axis * angle) of the shorter rotation, angle in [0, pi]
+  pub fn to_rotation_vector(self) -> Vector3<T> {
+    let q = if self.w() < T::zero() { -self } else { self };
+    let (angle, axis) = q.angle_axis();
+    axis.scalar_mul(angle)
+  }
+
+  pub fn new_from_rotation_vector(v: Vector3<T>) -> Quaternion<T> {
+    let angle = v.length();
+    if angle < T::epsilon() {
+      // first order expansion, sin(a / 2) / a ~ 1 / 2
+      let half = T::from_f64(0.5);
+      return Quaternion(T::one(), v.x() * half, v.y() * half, v.z() * half).unitize();
+    }
+    Quaternion::new_from_angle_axis(angle, v)
+  }
+
+  // e^q = e^w (cos|v|, v / |v| sin|v|)
+  pub fn exp(&self) -> Quaternion<T> {
+    let v = Vector3::from(*self);
+    let theta = v.length();
+    let scale = self.w().exp();
+    if theta < T::epsilon() {
+      return Quaternion(scale, v.x() * scale, v.y() * scale, v.z() * scale);
+    }
+    Quaternion::new_from_vec(scale * theta.cos(), v.scalar_mul(scale * theta.sin() / theta))
+  }
+
+  // principal logarithm, ln q = (ln|q|, v / |v| atan2(|v|, w)).
+  // for a unit quaternion this is the pure quaternion (0, axis * angle / 2).
+  // a negative real quaternion has angle pi about an arbitrary axis, x is used
+  // when v gives no direction at all.
+  pub fn ln(&self) -> Quaternion<T> {
+    let v = Vector3::from(*self);
+    let sin_part = v.length();
+    let norm = self.length();
+    if sin_part < T::epsilon() {
+      if self.w() < T::zero() {
+        let axis = if sin_part > T::zero() { v.scalar_div(sin_part) } else { Vector3::new(T::one(), T::zero(), T::zero()) };
+        return Quaternion::new_from_vec(norm.ln(), axis.scalar_mul(T::pi()));
+      }
+      return Quaternion(norm.ln(), v.x() / norm, v.y() / norm, v.z() / norm);
+    }
+    let theta = sin_part.atan2(self.w());
+    Quaternion::new_from_vec(norm.ln(), v.scalar_mul(theta / sin_part))
+  }
+
+  // q^t = exp(t ln q), for a unit quaternion a rotation by t times the angle
+  pub fn powf(&self, t: T) -> Quaternion<T> {
+    self.ln().scalar_mul(t).exp()
+  }
 
-    let sin_halve_theta = (angle * 0.5).sin();
+  // splits self into swing * twist, where twist rotates about axis and swing
+  // about an axis perpendicular to it. When self turns axis by exactly pi
+  // the twist is undefined and the identity is returned for it.
+  pub fn swing_twist(&self, axis: Vector3<T>) -> (Quaternion<T>, Quaternion<T>) {
+    let axis = axis.unitize();
+    let q = self.unitize();
+    let p = axis.scalar_mul(Vector3::from(q).dot(&axis));
+    let twist = Quaternion::new_from_vec(q.w(), p);
+    let twist = if twist.length() < T::epsilon() {
+      Quaternion::new(T::one(), T::zero(), T::zero(), T::zero())
+    } else {
+      twist.unitize()
+    };
+    (q * twist.conjugate(), twist)
+  }
 
-    let v = Vec3::from(self).scalar_div(sin_halve_theta);
+  // signed rotation angle about axis in (-pi, pi], self is expected to be a twist about axis
+  pub fn twist_angle(&self, axis: Vector3<T>) -> T {
+    let q = if self.w() < T::zero() { -*self } else { *self };
+    let s = Vector3::from(q).dot(&axis.unitize());
+    s.atan2(q.w()) * T::from_f64(2.0)
+  }
 
-    (angle, v)
+  // cone limit: keeps the swing axis but caps its angle at max_angle
+  pub fn clamp_swing(&self, max_angle: T) -> Quaternion<T> {
+    let r = self.to_rotation_vector();
+    let angle = r.length();
+    if angle <= max_angle {
+      return self.unitize();
+    }
+    Quaternion::new_from_rotation_vector(r.scalar_mul(max_angle / angle))
   }
 
-  pub fn rot_between_vecs(start: Vec3, dest: Vec3) -> Quat {
+  // clamps the twist angle about axis into [min_angle, max_angle]
+  pub fn clamp_twist(&self, axis: Vector3<T>, min_angle: T, max_angle: T) -> Quaternion<T> {
+    let angle = self.twist_angle(axis);
+    let clamped = angle.max(min_angle).min(max_angle);
+    Quaternion::new_from_angle_axis(clamped, axis)
+  }
+
+  // joint limit for a twist axis (e.g. along a bone): the swing stays within a
+  // cone of max_swing around the axis and the twist within [min_twist, max_twist]
+  pub fn clamp_swing_twist(&self, axis: Vector3<T>, max_swing: T, min_twist: T, max_twist: T) -> Quaternion<T> {
+    let (swing, twist) = self.swing_twist(axis);
+    swing.clamp_swing(max_swing) * twist.clamp_twist(axis, min_twist, max_twist)
+  }
+
+  pub fn rot_between_vecs(start: Vector3<T>, dest: Vector3<T>) -> Quaternion<T> {
     let start = start.unitize();
     let dest = dest.unitize();
 
     let cos_theta = start.dot(&dest);
 
-    if cos_theta < -1.0 + 0.001 {
-      let mut rot_axis = Vec3::unit_z() * start;
-      if rot_axis.length() < 0.01 {
-        rot_axis = Vec3::unit_x() * start;
+    if cos_theta < T::from_f64(-1.0 + 0.001) {
+      let mut rot_axis = Vector3::unit_z().cross(&start);
+      if rot_axis.length() < T::from_f64(0.01) {
+        rot_axis = Vector3::unit_x().cross(&start);
       }
       rot_axis = rot_axis.unitize();
 
       // make quat from angle and axis
-      Quat::new_from_angle_axis(180.0_f64.radians(), rot_axis)
+      Quaternion::new_from_angle_axis(T::pi(), rot_axis)
     } else {
 
-      let rot_axis = start * dest;
-      let s = ((1.0 + cos_theta) * 2.0).sqrt();
-      let invs = 1.0 / s;
+      let rot_axis = start.cross(&dest);
+      let s = ((T::one() + cos_theta) * T::from_f64(2.0)).sqrt();
+      let invs = T::one() / s;
 
-      Quat(
-        s * 0.5,
+      Quaternion(
+        s * T::from_f64(0.5),
         rot_axis.x()*invs,
         rot_axis.y()*invs,
         rot_axis.z()*invs)
     }
   }
+
+  // normalized linear interpolation, cheap but not constant speed.
+  // takes the shorter arc like slerp.
+  pub fn nlerp(&self, other: Quaternion<T>, t: T) -> Quaternion<T> {
+    let end = if self.dot(&other) < T::zero() { -other } else { other };
+    (self.scalar_mul(T::one() - t) + end.scalar_mul(t)).unitize()
+  }
+
+  // spherical linear interpolation along the shorter arc, constant angular speed.
+  // self and other are expected to be unit quaternions.
+  pub fn slerp(&self, other: Quaternion<T>, t: T) -> Quaternion<T> {
+    let end = if self.dot(&other) < T::zero() { -other } else { other };
+    self.slerp_no_flip(end, t)
+  }
+
+  // slerp without the hemisphere check, squad relies on this
+  fn slerp_no_flip(&self, other: Quaternion<T>, t: T) -> Quaternion<T> {
+    let cos_theta = self.dot(&other);
+    // nearly parallel: sin(theta) vanishes, fall back to nlerp
+    if cos_theta.abs() > T::from_f64(0.9995) {
+      return (self.scalar_mul(T::one() - t) + other.scalar_mul(t)).unitize();
+    }
+    let theta = cos_theta.acos();
+    let sin_theta = theta.sin();
+    let a = ((T::one() - t) * theta).sin() / sin_theta;
+    let b = (t * theta).sin() / sin_theta;
+    self.scalar_mul(a) + other.scalar_mul(b)
+  }
+
+  // spherical quadrangle interpolation between q0 and q1 with the inner
+  // control points a and b (see squad_control_point)
+  pub fn squad(q0: Quaternion<T>, a: Quaternion<T>, b: Quaternion<T>, q1: Quaternion<T>, t: T) -> Quaternion<T> {
+    let two = T::from_f64(2.0);
+    let outer = q0.slerp_no_flip(q1, t);
+    let inner = a.slerp_no_flip(b, t);
+    outer.slerp_no_flip(inner, two * t * (T::one() - t))
+  }
+
+  // inner control point for squad at key cur, so that the curve through
+  // prev, cur and next is C1 continuous:
+  // s = cur * exp(-(log(cur^-1 next) + log(cur^-1 prev)) / 4)
+  pub fn squad_control_point(prev: Quaternion<T>, cur: Quaternion<T>, next: Quaternion<T>) -> Quaternion<T> {
+    let inv = cur.conjugate();
+    let sum = (inv * next).ln() + (inv * prev).ln();
+    cur * sum.scalar_mul(T::from_f64(-0.25)).exp()
+  }
 }
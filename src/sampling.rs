@@ -0,0 +1,84 @@
+use super::quaternion::Quaternion;
+use super::scalar::Float;
+use super::vector::Vector3;
+
+// small deterministic generator (splitmix64), the same seed always gives the same sequence.
+// good enough for monte-carlo tests and view sampling, not for anything cryptographic.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Rng {
+        state: u64,
+}
+
+impl Rng {
+        pub fn new(seed: u64) -> Rng {
+                Rng { state: seed }
+        }
+
+        pub fn next_u64(&mut self) -> u64 {
+                self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+                let mut z = self.state;
+                z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+                z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+                z ^ (z >> 31)
+        }
+
+        // uniform in [0, 1) with 53 random bits
+        pub fn next_f64(&mut self) -> f64 {
+                (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+        }
+
+        pub fn next_float<T: Float>(&mut self) -> T {
+                T::from_f64(self.next_f64())
+        }
+}
+
+// uniformly distributed rotation (Shoemake, graphics gems III)
+pub fn uniform_quat<T: Float>(rng: &mut Rng) -> Quaternion<T> {
+        let two_pi = T::pi() * T::from_f64(2.0);
+        let u0: T = rng.next_float();
+        let u1: T = rng.next_float();
+        let u2: T = rng.next_float();
+        let r1 = (T::one() - u0).sqrt();
+        let r2 = u0.sqrt();
+        let (t1, t2) = (two_pi * u1, two_pi * u2);
+        Quaternion::new(r2 * t2.cos(), r1 * t1.sin(), r1 * t1.cos(), r2 * t2.sin())
+}
+
+// uniformly distributed unit vector
+pub fn uniform_sphere<T: Float>(rng: &mut Rng) -> Vector3<T> {
+        let z = T::one() - T::from_f64(2.0) * rng.next_float();
+        let phi = T::pi() * T::from_f64(2.0) * rng.next_float();
+        let r = (T::one() - z * z).max(T::zero()).sqrt();
+        Vector3::new(r * phi.cos(), r * phi.sin(), z)
+}
+
+// uniformly distributed unit vector with a non-negative component along normal
+pub fn uniform_hemisphere<T: Float>(rng: &mut Rng, normal: Vector3<T>) -> Vector3<T> {
+        let v = uniform_sphere(rng);
+        if v.dot(&normal) < T::zero() { -v } else { v }
+}
+
+// unit vector around normal with density cos(theta) / pi (Malley's method)
+pub fn cosine_hemisphere<T: Float>(rng: &mut Rng, normal: Vector3<T>) -> Vector3<T> {
+        let n = normal.unitize();
+        let (t, b) = n.orthonormal_basis();
+        let r = rng.next_float::<T>().sqrt();
+        let phi = T::pi() * T::from_f64(2.0) * rng.next_float();
+        let z = (T::one() - r * r).max(T::zero()).sqrt();
+        t.scalar_mul(r * phi.cos()) + b.scalar_mul(r * phi.sin()) + n.scalar_mul(z)
+}
+
+// n nearly evenly spaced unit vectors on a golden angle spiral from +z to -z
+pub fn fibonacci_sphere<T: Float>(n: usize) -> Vec<Vector3<T>> {
+        let golden_angle = T::pi() * (T::from_f64(3.0) - T::from_f64(5.0).sqrt());
+        let count = T::from_f64(n as f64);
+        (0..n)
+                .map(|i| {
+                        let i = T::from_f64(i as f64);
+                        let z = T::one() - (T::from_f64(2.0) * i + T::one()) / count;
+                        let r = (T::one() - z * z).max(T::zero()).sqrt();
+                        let phi = golden_angle * i;
+                        Vector3::new(r * phi.cos(), r * phi.sin(), z)
+                })
+                .collect()
+}
@@ -0,0 +1,139 @@
+use std::fmt::{Debug, Display};
+use std::ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Sub, SubAssign};
+
+// the floating point operations the geometry types need,
+// implemented for f32 and f64 so every type works in both precisions
+pub trait Float:
+	Copy
+	+ Debug
+	+ Display
+	+ PartialEq
+	+ PartialOrd
+	+ Add<Output = Self>
+	+ Sub<Output = Self>
+	+ Mul<Output = Self>
+	+ Div<Output = Self>
+	+ Neg<Output = Self>
+	+ AddAssign
+	+ SubAssign
+	+ MulAssign
+	+ DivAssign
+{
+	fn zero() -> Self;
+	fn one() -> Self;
+	fn pi() -> Self;
+	fn epsilon() -> Self;
+
+	// lossy when Self is f32
+	fn from_f64(v: f64) -> Self;
+	fn to_f64(self) -> f64;
+
+	fn sqrt(self) -> Self;
+	fn abs(self) -> Self;
+	fn signum(self) -> Self;
+	fn powi(self, n: i32) -> Self;
+	fn exp(self) -> Self;
+	fn ln(self) -> Self;
+	fn sin(self) -> Self;
+	fn cos(self) -> Self;
+	fn tan(self) -> Self;
+	fn asin(self) -> Self;
+	fn acos(self) -> Self;
+	fn atan2(self, other: Self) -> Self;
+	fn max(self, other: Self) -> Self;
+	fn min(self, other: Self) -> Self;
+	fn is_nan(self) -> bool;
+}
+
+macro_rules! impl_float {
+	($t:ident) => {
+		impl Float for $t {
+			fn zero() -> $t {
+				0.0
+			}
+
+			fn one() -> $t {
+				1.0
+			}
+
+			fn pi() -> $t {
+				::std::$t::consts::PI
+			}
+
+			fn epsilon() -> $t {
+				$t::EPSILON
+			}
+
+			fn from_f64(v: f64) -> $t {
+				v as $t
+			}
+
+			fn to_f64(self) -> f64 {
+				self as f64
+			}
+
+			fn sqrt(self) -> $t {
+				$t::sqrt(self)
+			}
+
+			fn abs(self) -> $t {
+				$t::abs(self)
+			}
+
+			fn signum(self) -> $t {
+				$t::signum(self)
+			}
+
+			fn powi(self, n: i32) -> $t {
+				$t::powi(self, n)
+			}
+
+			fn exp(self) -> $t {
+				$t::exp(self)
+			}
+
+			fn ln(self) -> $t {
+				$t::ln(self)
+			}
+
+			fn sin(self) -> $t {
+				$t::sin(self)
+			}
+
+			fn cos(self) -> $t {
+				$t::cos(self)
+			}
+
+			fn tan(self) -> $t {
+				$t::tan(self)
+			}
+
+			fn asin(self) -> $t {
+				$t::asin(self)
+			}
+
+			fn acos(self) -> $t {
+				$t::acos(self)
+			}
+
+			fn atan2(self, other: $t) -> $t {
+				$t::atan2(self, other)
+			}
+
+			fn max(self, other: $t) -> $t {
+				$t::max(self, other)
+			}
+
+			fn min(self, other: $t) -> $t {
+				$t::min(self, other)
+			}
+
+			fn is_nan(self) -> bool {
+				$t::is_nan(self)
+			}
+		}
+	};
+}
+
+impl_float!(f32);
+impl_float!(f64);
@@ -0,0 +1,61 @@
+use super::quaternion::Quaternion;
+use super::scalar::Float;
+
+// smooth interpolation through a sequence of key orientations.
+// consecutive keys are joined by squad segments whose control points are
+// derived from the neighbouring keys, the quaternion counterpart of a
+// Catmull-Rom spline: it passes through every key with continuous angular velocity.
+#[derive(Debug, Clone)]
+pub struct QuatSpline<T> {
+  keys: Vec<Quaternion<T>>,
+  controls: Vec<Quaternion<T>>,
+}
+
+impl<T: Float> QuatSpline<T> {
+  // keys are unitized and flipped into a common hemisphere so every segment takes the short arc.
+  // panics when keys is empty.
+  pub fn new(keys: &[Quaternion<T>]) -> QuatSpline<T> {
+    assert!(!keys.is_empty(), "QuatSpline needs at least one key");
+
+    let mut aligned: Vec<Quaternion<T>> = Vec::with_capacity(keys.len());
+    for k in keys {
+      let k = k.unitize();
+      let k = match aligned.last() {
+        Some(prev) if prev.dot(&k) < T::zero() => -k,
+        _ => k,
+      };
+      aligned.push(k);
+    }
+
+    // end keys are their own neighbours
+    let n = aligned.len();
+    let controls = (0..n)
+      .map(|i| {
+        let prev = aligned[if i == 0 { 0 } else { i - 1 }];
+        let next = aligned[if i + 1 == n { i } else { i + 1 }];
+        Quaternion::squad_control_point(prev, aligned[i], next)
+      })
+      .collect();
+
+    QuatSpline { keys: aligned, controls }
+  }
+
+  pub fn keys(&self) -> &[Quaternion<T>] {
+    &self.keys
+  }
+
+  // t runs from 0 (first key) to keys().len() - 1 (last key), clamped outside that range
+  pub fn sample(&self, t: T) -> Quaternion<T> {
+    let last = self.keys.len() - 1;
+    if last == 0 || t <= T::zero() {
+      return self.keys[0];
+    }
+    if t >= T::from_f64(last as f64) {
+      return self.keys[last];
+    }
+
+    let i = (t.to_f64().floor() as usize).min(last - 1);
+    let local = t - T::from_f64(i as f64);
+    Quaternion::squad(self.keys[i], self.controls[i], self.controls[i + 1], self.keys[i + 1], local)
+  }
+}
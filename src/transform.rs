@@ -0,0 +1,184 @@
+use std::ops::Mul;
+use std::convert::From;
+use super::matrix3::Matrix3;
+use super::matrix4::Matrix4;
+use super::quaternion::Quaternion;
+use super::vector::Vector3;
+use super::scalar::Float;
+
+// affine transform stored as a 4x4 matrix with (0, 0, 0, 1) as the last row.
+// built as translation * rotation * scale, so scale applies first.
+#[derive(Debug, Clone, Copy)]
+pub struct Affine3<T> {
+        m: Matrix4<T>,
+}
+
+pub type Transform = Affine3<f64>;
+pub type Transformf = Affine3<f32>;
+
+// translation, rotation, scale and shear of an affine matrix, recomposed as
+// translation * rotation * shear * scale. shear is (xy, xz, yz), the unit upper
+// triangular factor [[1, xy, xz], [0, 1, yz], [0, 0, 1]].
+// a mirrored matrix is represented with a negative x scale and reflection set.
+#[derive(Debug, Clone, Copy)]
+pub struct Trs<T> {
+        pub translation: Vector3<T>,
+        pub rotation: Quaternion<T>,
+        pub scale: Vector3<T>,
+        pub shear: Vector3<T>,
+        pub reflection: bool,
+}
+
+impl<T: Float> Trs<T> {
+        pub fn matrix(&self) -> Matrix4<T> {
+                let one = T::one();
+                let zero = T::zero();
+                let shear = Matrix3 { v: [
+                        one, self.shear.x(), self.shear.y(),
+                        zero, one, self.shear.z(),
+                        zero, zero, one ] };
+                Matrix4::new_translation(self.translation)
+                        * Matrix4::from(Matrix3::from(self.rotation.unitize()))
+                        * Matrix4::from(shear)
+                        * Matrix4::new_scale(self.scale)
+        }
+}
+
+impl<T: Float> Matrix4<T> {
+        // splits the linear part with a QR decomposition. Returns None for a non-affine
+        // matrix (last row other than (0, 0, 0, 1)) or a singular linear part.
+        pub fn decompose_trs(&self) -> Option<Trs<T>> {
+                let affine = Affine3::try_from_mat4(*self)?;
+                let linear = affine.linear();
+                let scale_hint = linear.v.iter().fold(T::zero(), |m, e| m.max(e.abs()));
+                if linear.determinant().abs() <= T::epsilon() * scale_hint * scale_hint * scale_hint {
+                        return None;
+                }
+
+                // linear = q * k with k upper triangular; make the diagonal of k positive
+                let (q, k) = linear.qr();
+                let mut q = q;
+                let mut k = k;
+                for i in 0..3 {
+                        if k.v[i * 4] < T::zero() {
+                                for j in 0..3 {
+                                        q.v[j * 3 + i] = -q.v[j * 3 + i];
+                                        k.v[i * 3 + j] = -k.v[i * 3 + j];
+                                }
+                        }
+                }
+                // move a mirror out of q into the first row of k
+                let reflection = q.determinant() < T::zero();
+                if reflection {
+                        for j in 0..3 {
+                                q.v[j * 3] = -q.v[j * 3];
+                                k.v[j] = -k.v[j];
+                        }
+                }
+
+                let scale = Vector3::new(k.v[0], k.v[4], k.v[8]);
+                let shear = Vector3::new(k.v[1] / scale.y(), k.v[2] / scale.z(), k.v[5] / scale.z());
+                Some(Trs {
+                        translation: affine.translation(),
+                        rotation: Quaternion::from(q),
+                        scale,
+                        shear,
+                        reflection,
+                })
+        }
+}
+
+impl<T: Float> Mul for Affine3<T> {
+        type Output = Affine3<T>;
+
+        // (a * b) applies b first, then a
+        fn mul(self, other: Affine3<T>) -> Affine3<T> {
+                Affine3 { m: self.m * other.m }
+        }
+}
+
+impl<T: Float> From<Affine3<T>> for Matrix4<T> {
+        fn from(t: Affine3<T>) -> Matrix4<T> {
+                t.m
+        }
+}
+
+impl<T: Float> Affine3<T> {
+        pub fn identity() -> Affine3<T> {
+                Affine3 { m: Matrix4::identity() }
+        }
+
+        pub fn new(rotation: Matrix3<T>, translation: Vector3<T>, scale: Vector3<T>) -> Affine3<T> {
+                let m = Matrix4::new_translation(translation)
+                        * Matrix4::from(rotation)
+                        * Matrix4::new_scale(scale);
+                Affine3 { m }
+        }
+
+        pub fn new_from_quat(rotation: Quaternion<T>, translation: Vector3<T>, scale: Vector3<T>) -> Affine3<T> {
+                Affine3::new(Matrix3::from(rotation.unitize()), translation, scale)
+        }
+
+        pub fn new_translation(t: Vector3<T>) -> Affine3<T> {
+                Affine3 { m: Matrix4::new_translation(t) }
+        }
+
+        pub fn new_rotation(r: Matrix3<T>) -> Affine3<T> {
+                Affine3 { m: Matrix4::from(r) }
+        }
+
+        pub fn new_scale(s: Vector3<T>) -> Affine3<T> {
+                Affine3 { m: Matrix4::new_scale(s) }
+        }
+
+        // returns None for a non-affine matrix (last row other than (0, 0, 0, 1))
+        pub fn try_from_mat4(m: Matrix4<T>) -> Option<Affine3<T>> {
+                let zero = T::zero();
+                if m.m30() == zero && m.m31() == zero && m.m32() == zero && m.m33() == T::one() {
+                        Some(Affine3 { m })
+                } else {
+                        None
+                }
+        }
+
+        pub fn matrix(&self) -> Matrix4<T> {
+                self.m
+        }
+
+        pub fn linear(&self) -> Matrix3<T> {
+                self.m.linear()
+        }
+
+        pub fn translation(&self) -> Vector3<T> {
+                self.m.translation()
+        }
+
+        // inverts the 3x3 block only, instead of the full 4x4 matrix:
+        // [A t]^-1 = [A^-1 -A^-1 t]
+        pub fn try_inverse(&self) -> Option<Affine3<T>> {
+                let inv = self.linear().try_inverse()?;
+                let t = self.translation().apply_rot_mat3(inv).scalar_mul(-T::one());
+                let m = Matrix4::new_translation(t) * Matrix4::from(inv);
+                Some(Affine3 { m })
+        }
+
+        // points are affected by translation
+        pub fn transform_point(&self, p: Vector3<T>) -> Vector3<T> {
+                self.m.transform_point(p)
+        }
+
+        // directions (offsets between points) ignore translation
+        pub fn transform_vector(&self, d: Vector3<T>) -> Vector3<T> {
+                self.m.transform_vector(d)
+        }
+
+        // normals go through the inverse transpose so they stay perpendicular
+        // to transformed surfaces under non-uniform scale. The result is unitized,
+        // a singular transform leaves the direction unchanged.
+        pub fn transform_normal(&self, n: Vector3<T>) -> Vector3<T> {
+                match self.linear().try_inverse() {
+                        Some(inv) => n.apply_rot_mat3(inv.transpose()).unitize(),
+                        None => n.unitize(),
+                }
+        }
+}
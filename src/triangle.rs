@@ -0,0 +1,222 @@
+use std::convert::From;
+use super::primitive::{Plane, Ray};
+use super::scalar::Float;
+use super::vector::Vector3;
+
+// counter-clockwise a, b, c seen from the side the normal points to
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Triangle<T> {
+        pub a: Vector3<T>,
+        pub b: Vector3<T>,
+        pub c: Vector3<T>,
+}
+
+// which sides of a triangle a ray may hit
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Culling {
+        // only hits from the side the normal points to
+        Back,
+        // only hits from behind
+        Front,
+        Disabled,
+}
+
+// ray parameter, point, geometric normal and barycentric (u, v) of the hit, with
+// point = (1 - u - v) * a + u * b + v * c. front_face is set when the ray came from
+// the side the normal points to.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct TriangleHit<T> {
+        pub t: T,
+        pub point: Vector3<T>,
+        pub normal: Vector3<T>,
+        pub u: T,
+        pub v: T,
+        pub front_face: bool,
+}
+
+impl<T: Float> From<[Vector3<T>; 3]> for Triangle<T> {
+        fn from(v: [Vector3<T>; 3]) -> Triangle<T> {
+                Triangle::new(v[0], v[1], v[2])
+        }
+}
+
+impl<T: Float> From<Triangle<T>> for [Vector3<T>; 3] {
+        fn from(t: Triangle<T>) -> [Vector3<T>; 3] {
+                t.vertices()
+        }
+}
+
+impl<T: Float> Triangle<T> {
+        pub fn new(a: Vector3<T>, b: Vector3<T>, c: Vector3<T>) -> Triangle<T> {
+                Triangle { a, b, c }
+        }
+
+        pub fn vertices(&self) -> [Vector3<T>; 3] {
+                [self.a, self.b, self.c]
+        }
+
+        // (b - a) x (c - a), twice the area long
+        pub fn scaled_normal(&self) -> Vector3<T> {
+                (self.b - self.a).cross(&(self.c - self.a))
+        }
+
+        // unit normal, NaN for a degenerate triangle
+        pub fn normal(&self) -> Vector3<T> {
+                Vector3::normal_from_three_vertices(self.a, self.b, self.c)
+        }
+
+        pub fn area(&self) -> T {
+                self.scaled_normal().length() * T::from_f64(0.5)
+        }
+
+        pub fn centroid(&self) -> Vector3<T> {
+                Vector3::mean_from_three_vertices(self.a, self.b, self.c)
+        }
+
+        pub fn is_degenerate(&self) -> bool {
+                let n = self.scaled_normal();
+                let scale = (self.b - self.a).dot(&(self.b - self.a)).max((self.c - self.a).dot(&(self.c - self.a)));
+                n.dot(&n) <= T::epsilon() * T::epsilon() * scale * scale
+        }
+
+        pub fn plane(&self) -> Option<Plane<T>> {
+                Plane::new_from_three_points(self.a, self.b, self.c)
+        }
+
+        // weights (wa, wb, wc) summing to one with wa * a + wb * b + wc * c the projection
+        // of p onto the triangle's plane. outside the triangle some weight is negative.
+        // None for a degenerate triangle.
+        pub fn barycentric(&self, p: Vector3<T>) -> Option<(T, T, T)> {
+                if self.is_degenerate() {
+                        return None;
+                }
+                let v0 = self.b - self.a;
+                let v1 = self.c - self.a;
+                let v2 = p - self.a;
+                let d00 = v0.dot(&v0);
+                let d01 = v0.dot(&v1);
+                let d11 = v1.dot(&v1);
+                let d20 = v2.dot(&v0);
+                let d21 = v2.dot(&v1);
+                let denom = d00 * d11 - d01 * d01;
+                let v = (d11 * d20 - d01 * d21) / denom;
+                let w = (d00 * d21 - d01 * d20) / denom;
+                Some((T::one() - v - w, v, w))
+        }
+
+        // Ericson, real-time collision detection 5.1.5
+        pub fn closest_point(&self, p: Vector3<T>) -> Vector3<T> {
+                let (a, b, c) = (self.a, self.b, self.c);
+                let ab = b - a;
+                let ac = c - a;
+                let ap = p - a;
+                let d1 = ab.dot(&ap);
+                let d2 = ac.dot(&ap);
+                if d1 <= T::zero() && d2 <= T::zero() {
+                        return a;
+                }
+                let bp = p - b;
+                let d3 = ab.dot(&bp);
+                let d4 = ac.dot(&bp);
+                if d3 >= T::zero() && d4 <= d3 {
+                        return b;
+                }
+                let vc = d1 * d4 - d3 * d2;
+                if vc <= T::zero() && d1 >= T::zero() && d3 <= T::zero() {
+                        return a + ab.scalar_mul(d1 / (d1 - d3));
+                }
+                let cp = p - c;
+                let d5 = ab.dot(&cp);
+                let d6 = ac.dot(&cp);
+                if d6 >= T::zero() && d5 <= d6 {
+                        return c;
+                }
+                let vb = d5 * d2 - d1 * d6;
+                if vb <= T::zero() && d2 >= T::zero() && d6 <= T::zero() {
+                        return a + ac.scalar_mul(d2 / (d2 - d6));
+                }
+                let va = d3 * d6 - d5 * d4;
+                if va <= T::zero() && (d4 - d3) >= T::zero() && (d5 - d6) >= T::zero() {
+                        return b + (c - b).scalar_mul((d4 - d3) / ((d4 - d3) + (d5 - d6)));
+                }
+                let denom = T::one() / (va + vb + vc);
+                a + ab.scalar_mul(vb * denom) + ac.scalar_mul(vc * denom)
+        }
+
+        // Moller-Trumbore. Edges and vertices count as hits, rays in the triangle's
+        // plane never hit.
+        pub fn intersect_ray(&self, ray: &Ray<T>, culling: Culling) -> Option<TriangleHit<T>> {
+                let e1 = self.b - self.a;
+                let e2 = self.c - self.a;
+                let p = ray.direction.cross(&e2);
+                let det = e1.dot(&p);
+                let scale = e1.length() * e2.length() * ray.direction.length();
+                if det.abs() <= T::epsilon() * scale {
+                        return None;
+                }
+                // det > 0 when the ray travels against the normal
+                let front_face = det > T::zero();
+                match culling {
+                        Culling::Back if !front_face => return None,
+                        Culling::Front if front_face => return None,
+                        _ => {}
+                }
+                let inv = T::one() / det;
+                let s = ray.origin - self.a;
+                let u = s.dot(&p) * inv;
+                if u < T::zero() || u > T::one() {
+                        return None;
+                }
+                let q = s.cross(&e1);
+                let v = ray.direction.dot(&q) * inv;
+                if v < T::zero() || u + v > T::one() {
+                        return None;
+                }
+                let t = e2.dot(&q) * inv;
+                if t < T::zero() {
+                        return None;
+                }
+                Some(TriangleHit { t, point: ray.at(t), normal: e1.cross(&e2).unitize(), u, v, front_face })
+        }
+
+        // separating axis test over both normals, the nine edge cross products and the
+        // in-plane edge normals (for coplanar pairs). Touching triangles overlap.
+        pub fn intersects_triangle(&self, other: &Triangle<T>) -> bool {
+                let ea = [self.b - self.a, self.c - self.b, self.a - self.c];
+                let eb = [other.b - other.a, other.c - other.b, other.a - other.c];
+                let na = self.scaled_normal();
+                let nb = other.scaled_normal();
+
+                // both normals, the nine edge pairs and the in-plane edge normals of each side
+                let mut axes = [na; 17];
+                axes[1] = nb;
+                for (i, e) in ea.iter().enumerate() {
+                        for (j, f) in eb.iter().enumerate() {
+                                axes[2 + 3 * i + j] = e.cross(f);
+                        }
+                        axes[11 + i] = na.cross(e);
+                        axes[14 + i] = nb.cross(&eb[i]);
+                }
+
+                let va = self.vertices();
+                let vb = other.vertices();
+                let project = |vs: &[Vector3<T>; 3], axis: &Vector3<T>| {
+                        vs.iter().fold((T::from_f64(f64::INFINITY), -T::from_f64(f64::INFINITY)), |(lo, hi), v| {
+                                let d = v.dot(axis);
+                                (lo.min(d), hi.max(d))
+                        })
+                };
+                let size = ea.iter().chain(eb.iter()).fold(T::zero(), |m, e| m.max(e.length()));
+                let tolerance = T::epsilon().sqrt() * size;
+                !axes.iter().any(|axis| {
+                        let length = axis.length();
+                        if length <= tolerance * size {
+                                return false;
+                        }
+                        let (min_a, max_a) = project(&va, axis);
+                        let (min_b, max_b) = project(&vb, axis);
+                        let gap = tolerance * length;
+                        max_a + gap < min_b || max_b + gap < min_a
+                })
+        }
+}
@@ -1,20 +1,24 @@
 use std::fmt;
-use std::f64::consts::PI;
-use std::ops::{Add, Mul, Sub};
+use std::iter::Sum;
+use std::ops::{Add, AddAssign, Div, DivAssign, Index, IndexMut, Mul, MulAssign, Neg, Sub, SubAssign};
 use std::convert::From;
 
-use super::quaternion::Quat;
-use super::matrix3::Mat3;
+use super::quaternion::Quaternion;
+use super::matrix3::Matrix3;
+use super::scalar::Float;
 
 #[derive(Debug, Copy, Clone, PartialEq)]
-pub struct Vec3 (f64, f64, f64);
+pub struct Vector3<T> (T, T, T);
 
-impl Add for Vec3 {
-	type Output = Vec3;
+pub type Vec3 = Vector3<f64>;
+pub type Vec3f = Vector3<f32>;
 
-	fn add (self, other:Vec3) -> Vec3 {
+impl<T: Float> Add for Vector3<T> {
+	type Output = Vector3<T>;
+
+	fn add (self, other:Vector3<T>) -> Vector3<T> {
 		// this is the cross product
-		Vec3(
+		Vector3(
 			self.0 + other.0,
 			self.1 + other.1,
 			self.2 + other.2
@@ -22,149 +26,464 @@ impl Add for Vec3 {
 	}
 }
 
-impl Mul for Vec3 {
-	type Output = Vec3;
+impl<T: Float> Sub for Vector3<T> {
+	type Output = Vector3<T>;
 
-	fn mul (self, other:Vec3) -> Vec3 {
+	fn sub (self, other: Vector3<T>) -> Vector3<T> {
 		// this is the cross product
-		Vec3(
-			self.1 * other.2 - self.2 * other.1,
-			self.2 * other.0 - self.0 * other.2,
-			self.0 * other.1 - self.1 * other.0
+		Vector3(
+			self.0 - other.0,
+			self.1 - other.1,
+			self.2 - other.2
 		)
 	}
 }
 
-impl Sub for Vec3 {
-	type Output = Vec3;
+impl<T: Float> Neg for Vector3<T> {
+	type Output = Vector3<T>;
 
-	fn sub (self, other: Vec3) -> Vec3 {
-		// this is the cross product
-		Vec3(
-			self.0 - other.0,
-			self.1 - other.1,
-			self.2 - other.2
-		)
+	fn neg (self) -> Vector3<T> {
+		Vector3(-self.0, -self.1, -self.2)
+	}
+}
+
+impl<T: Float> Mul<T> for Vector3<T> {
+	type Output = Vector3<T>;
+
+	fn mul (self, s:T) -> Vector3<T> {
+		self.scalar_mul(s)
 	}
 }
-impl From<Quat> for Vec3 {
-	fn from(original: Quat) -> Vec3 {
-		Vec3(original.x(), original.y(), original.z())
+
+impl<T: Float> Div<T> for Vector3<T> {
+	type Output = Vector3<T>;
+
+	fn div (self, s:T) -> Vector3<T> {
+		self.scalar_div(s)
 	}
 }
 
-impl Vec3 {
-	pub fn new(x:f64, y:f64, z:f64) -> Vec3{Vec3(x, y, z)}	
+macro_rules! impl_scalar_lhs_mul {
+	($t:ident) => {
+		impl Mul<Vector3<$t>> for $t {
+			type Output = Vector3<$t>;
+
+			fn mul (self, v:Vector3<$t>) -> Vector3<$t> {
+				v.scalar_mul(self)
+			}
+		}
+	};
+}
+
+impl_scalar_lhs_mul!(f32);
+impl_scalar_lhs_mul!(f64);
+
+impl<T: Float> AddAssign for Vector3<T> {
+	fn add_assign (&mut self, other:Vector3<T>) {
+		*self = *self + other;
+	}
+}
+
+impl<T: Float> SubAssign for Vector3<T> {
+	fn sub_assign (&mut self, other:Vector3<T>) {
+		*self = *self - other;
+	}
+}
+
+impl<T: Float> MulAssign<T> for Vector3<T> {
+	fn mul_assign (&mut self, s:T) {
+		*self = self.scalar_mul(s);
+	}
+}
+
+impl<T: Float> DivAssign<T> for Vector3<T> {
+	fn div_assign (&mut self, s:T) {
+		*self = self.scalar_div(s);
+	}
+}
+
+impl<T: Float> Index<usize> for Vector3<T> {
+	type Output = T;
+
+	fn index (&self, i:usize) -> &T {
+		match i {
+			0 => &self.0,
+			1 => &self.1,
+			2 => &self.2,
+			_ => panic!("index {} out of range for Vector3", i),
+		}
+	}
+}
+
+impl<T: Float> IndexMut<usize> for Vector3<T> {
+	fn index_mut (&mut self, i:usize) -> &mut T {
+		match i {
+			0 => &mut self.0,
+			1 => &mut self.1,
+			2 => &mut self.2,
+			_ => panic!("index {} out of range for Vector3", i),
+		}
+	}
+}
+
+impl<T: Float> Sum for Vector3<T> {
+	fn sum<I: Iterator<Item = Vector3<T>>>(iter: I) -> Vector3<T> {
+		iter.fold(Vector3::zero(), |a, b| a + b)
+	}
+}
+
+impl<'a, T: Float> Sum<&'a Vector3<T>> for Vector3<T> {
+	fn sum<I: Iterator<Item = &'a Vector3<T>>>(iter: I) -> Vector3<T> {
+		iter.fold(Vector3::zero(), |a, b| a + *b)
+	}
+}
+
+impl<T: Float> From<Quaternion<T>> for Vector3<T> {
+	fn from(original: Quaternion<T>) -> Vector3<T> {
+		Vector3(original.x(), original.y(), original.z())
+	}
+}
+
+// widening is lossless, narrowing goes through cast()
+impl From<Vector3<f32>> for Vector3<f64> {
+	fn from(o: Vector3<f32>) -> Vector3<f64> {
+		o.cast()
+	}
+}
+
+impl<T: Float> Vector3<T> {
+	pub fn new(x:T, y:T, z:T) -> Vector3<T>{Vector3(x, y, z)}	
 
 	#[allow(dead_code)]
-	pub fn zero() -> Vec3{Vec3(0.0, 0.0, 0.0)}	
+	pub fn zero() -> Vector3<T>{Vector3(T::zero(), T::zero(), T::zero())}	
 
 	#[allow(dead_code)]
-	pub fn unit_x() -> Vec3{Vec3(1.0, 0.0, 0.0)}	
+	pub fn unit_x() -> Vector3<T>{Vector3(T::one(), T::zero(), T::zero())}	
 
 	#[allow(dead_code)]
-	pub fn unit_z() -> Vec3{Vec3(0.0, 0.0, 1.0)}	
+	pub fn unit_z() -> Vector3<T>{Vector3(T::zero(), T::zero(), T::one())}	
+
+	// converts between precisions, rounding when narrowing to f32
+	pub fn cast<U: Float>(&self) -> Vector3<U> {
+		Vector3(
+			U::from_f64(self.0.to_f64()),
+			U::from_f64(self.1.to_f64()),
+			U::from_f64(self.2.to_f64())
+		)
+	}
 
-	pub fn normal_from_three_vertices(a:Vec3, b: Vec3, c:Vec3) -> Vec3{
+	pub fn normal_from_three_vertices(a:Vector3<T>, b: Vector3<T>, c:Vector3<T>) -> Vector3<T>{
 		let ab = b - a;
 		let ac = c - a;
-		(ab * ac).unitize()
+		ab.cross(&ac).unitize()
 	}
 
-	// FIXME: not generic
-	pub fn normal_from_vertex_array(v:[Vec3;3]) -> Vec3{
-		Vec3::normal_from_three_vertices(v[0], v[1], v[2])
+	pub fn normal_from_vertex_array(v:[Vector3<T>;3]) -> Vector3<T>{
+		Vector3::normal_from_three_vertices(v[0], v[1], v[2])
 	}
 
-	pub fn mean_from_three_vertices(a:Vec3, b: Vec3, c:Vec3) -> Vec3{
+	pub fn mean_from_three_vertices(a:Vector3<T>, b: Vector3<T>, c:Vector3<T>) -> Vector3<T>{
 		let sum = a + b + c;
-		sum.scalar_div(3.0)
+		sum.scalar_div(T::from_f64(3.0))
 	}
 
-	// FIXME: not generic
-	pub fn mean_from_vertex_array(v:[Vec3;3]) -> Vec3{
-		Vec3::mean_from_three_vertices(v[0], v[1], v[2])
+	pub fn mean_from_vertex_array(v:[Vector3<T>;3]) -> Vector3<T>{
+		Vector3::mean_from_three_vertices(v[0], v[1], v[2])
 	}
 
-	pub fn x(&self) -> f64 {
+	pub fn x(&self) -> T {
 		self.0
 	}
 
-	pub fn y(&self) -> f64 {
+	pub fn y(&self) -> T {
 		self.1
 	}
 
-	pub fn z(&self) -> f64 {
+	pub fn z(&self) -> T {
 		self.2
 	}
 
-	pub fn apply_rot_mat3(&self, m:Mat3) -> Vec3 {
-		Vec3(
+	pub fn apply_rot_mat3(&self, m:Matrix3<T>) -> Vector3<T> {
+		Vector3(
 			self.0 * m.m00() + self.1 * m.m01() + self.2 * m.m02(),
 			self.0 * m.m10() + self.1 * m.m11() + self.2 * m.m12(),
 			self.0 * m.m20() + self.1 * m.m21() + self.2 * m.m22(),
 		)
 	}
 
-	pub fn distance(self, other:Vec3) -> f64 {
+	pub fn distance(self, other:Vector3<T>) -> T {
 		(self - other).length()
 	}
 
 
-	pub fn dot(&self, other: &Vec3) -> f64 {
+	pub fn dot(&self, other: &Vector3<T>) -> T {
 		self.0 * other.0 + self.1 * other.1 + self.2 * other.2
 	}
 
-	// FIXME: can I override ops::Mul??
-	pub fn scalar_mul(&self, s:f64) -> Vec3{
-		Vec3(
+	pub fn cross(&self, other: &Vector3<T>) -> Vector3<T> {
+		Vector3(
+			self.1 * other.2 - self.2 * other.1,
+			self.2 * other.0 - self.0 * other.2,
+			self.0 * other.1 - self.1 * other.0
+		)
+	}
+
+	pub fn scalar_mul(&self, s:T) -> Vector3<T>{
+		Vector3(
 			self.0 * s,
 			self.1 * s,
 			self.2 * s
 		)
 	}
 
-	pub fn scalar_div(&self, s:f64) -> Vec3{
-		Vec3(
+	pub fn scalar_div(&self, s:T) -> Vector3<T>{
+		Vector3(
 			self.0 / s,
 			self.1 / s,
 			self.2 / s
 		)
 	}
 
-	pub fn length (&self) -> f64 {
+	pub fn length (&self) -> T {
 		(self.0 * self.0 + self.1 * self.1 + self.2 *self.2).sqrt()
 	}
 
 	// normalize??
-	pub fn unitize(&self) -> Vec3 {
+	pub fn unitize(&self) -> Vector3<T> {
 		let length = self.length(); 
-		Vec3(self.0/length, self.1/length, self.2/length)	
+		Vector3(self.0/length, self.1/length, self.2/length)	
 	}
 
-	pub fn angle(&self, other: &Vec3) -> f64 {
+	pub fn angle(&self, other: &Vector3<T>) -> T {
 		let d = self.dot(other);
 		let lengths  = self.length() * other.length();
 		(d/lengths).acos()		
 	}
+
+	// orthonormal tangents (t, b) with t x b = n for a unit n (Duff et al. 2017)
+	pub fn orthonormal_basis(&self) -> (Vector3<T>, Vector3<T>) {
+		let one = T::one();
+		let n = self;
+		let sign = if n.2 < T::zero() { -one } else { one };
+		let a = -one / (sign + n.2);
+		let b = n.0 * n.1 * a;
+		let t = Vector3(one + sign * n.0 * n.0 * a, sign * b, -sign * n.0);
+		let bt = Vector3(b, sign + n.1 * n.1 * a, -n.1);
+		(t, bt)
+	}
+}
+
+// homogeneous coordinates, w = 1 for points and w = 0 for directions
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Vector4<T> (T, T, T, T);
+
+pub type Vec4 = Vector4<f64>;
+pub type Vec4f = Vector4<f32>;
+
+impl<T: Float> Add for Vector4<T> {
+	type Output = Vector4<T>;
+
+	fn add (self, other:Vector4<T>) -> Vector4<T> {
+		Vector4(
+			self.0 + other.0,
+			self.1 + other.1,
+			self.2 + other.2,
+			self.3 + other.3
+		)
+	}
+}
+
+impl<T: Float> Sub for Vector4<T> {
+	type Output = Vector4<T>;
+
+	fn sub (self, other:Vector4<T>) -> Vector4<T> {
+		Vector4(
+			self.0 - other.0,
+			self.1 - other.1,
+			self.2 - other.2,
+			self.3 - other.3
+		)
+	}
+}
+
+impl<T: Float> Neg for Vector4<T> {
+	type Output = Vector4<T>;
+
+	fn neg (self) -> Vector4<T> {
+		Vector4(-self.0, -self.1, -self.2, -self.3)
+	}
+}
+
+impl<T: Float> Mul<T> for Vector4<T> {
+	type Output = Vector4<T>;
+
+	fn mul (self, s:T) -> Vector4<T> {
+		self.scalar_mul(s)
+	}
+}
+
+impl<T: Float> Div<T> for Vector4<T> {
+	type Output = Vector4<T>;
+
+	fn div (self, s:T) -> Vector4<T> {
+		Vector4(self.0 / s, self.1 / s, self.2 / s, self.3 / s)
+	}
+}
+
+impl<T: Float> AddAssign for Vector4<T> {
+	fn add_assign (&mut self, other:Vector4<T>) {
+		*self = *self + other;
+	}
+}
+
+impl<T: Float> SubAssign for Vector4<T> {
+	fn sub_assign (&mut self, other:Vector4<T>) {
+		*self = *self - other;
+	}
+}
+
+impl<T: Float> MulAssign<T> for Vector4<T> {
+	fn mul_assign (&mut self, s:T) {
+		*self = self.scalar_mul(s);
+	}
+}
+
+impl<T: Float> DivAssign<T> for Vector4<T> {
+	fn div_assign (&mut self, s:T) {
+		*self = *self / s;
+	}
+}
+
+impl<T: Float> Index<usize> for Vector4<T> {
+	type Output = T;
+
+	fn index (&self, i:usize) -> &T {
+		match i {
+			0 => &self.0,
+			1 => &self.1,
+			2 => &self.2,
+			3 => &self.3,
+			_ => panic!("index {} out of range for Vector4", i),
+		}
+	}
+}
+
+impl<T: Float> IndexMut<usize> for Vector4<T> {
+	fn index_mut (&mut self, i:usize) -> &mut T {
+		match i {
+			0 => &mut self.0,
+			1 => &mut self.1,
+			2 => &mut self.2,
+			3 => &mut self.3,
+			_ => panic!("index {} out of range for Vector4", i),
+		}
+	}
+}
+
+impl<T: Float> Sum for Vector4<T> {
+	fn sum<I: Iterator<Item = Vector4<T>>>(iter: I) -> Vector4<T> {
+		let zero = T::zero();
+		iter.fold(Vector4(zero, zero, zero, zero), |a, b| a + b)
+	}
+}
+
+impl From<Vector4<f32>> for Vector4<f64> {
+	fn from(o: Vector4<f32>) -> Vector4<f64> {
+		o.cast()
+	}
+}
+
+impl<T: Float> Vector4<T> {
+	pub fn new(x:T, y:T, z:T, w:T) -> Vector4<T>{Vector4(x, y, z, w)}
+
+	pub fn new_point(p:Vector3<T>) -> Vector4<T>{Vector4(p.0, p.1, p.2, T::one())}
+
+	pub fn new_direction(d:Vector3<T>) -> Vector4<T>{Vector4(d.0, d.1, d.2, T::zero())}
+
+	pub fn cast<U: Float>(&self) -> Vector4<U> {
+		Vector4(
+			U::from_f64(self.0.to_f64()),
+			U::from_f64(self.1.to_f64()),
+			U::from_f64(self.2.to_f64()),
+			U::from_f64(self.3.to_f64())
+		)
+	}
+
+	pub fn x(&self) -> T {
+		self.0
+	}
+
+	pub fn y(&self) -> T {
+		self.1
+	}
+
+	pub fn z(&self) -> T {
+		self.2
+	}
+
+	pub fn w(&self) -> T {
+		self.3
+	}
+
+	pub fn dot(&self, other: &Vector4<T>) -> T {
+		self.0 * other.0 + self.1 * other.1 + self.2 * other.2 + self.3 * other.3
+	}
+
+	pub fn scalar_mul(&self, s:T) -> Vector4<T>{
+		Vector4(self.0 * s, self.1 * s, self.2 * s, self.3 * s)
+	}
+
+	pub fn length (&self) -> T {
+		self.dot(self).sqrt()
+	}
+
+	// drops w without dividing
+	pub fn xyz(&self) -> Vector3<T> {
+		Vector3(self.0, self.1, self.2)
+	}
+
+	// back to 3d by dividing through w, None for points at infinity (w = 0)
+	pub fn perspective_divide(&self) -> Option<Vector3<T>> {
+		if self.3 == T::zero() {
+			None
+		} else {
+			Some(Vector3(self.0 / self.3, self.1 / self.3, self.2 / self.3))
+		}
+	}
+}
+
+impl<T: Float> fmt::Display for Vector4<T> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "({}, {}, {}, {})", self.0, self.1, self.2, self.3)
+	}
 }
 
 pub trait Angle {		
-	fn degrees(&self) -> f64;
-	fn radians(&self) -> f64;	
+	fn degrees(&self) -> Self;
+	fn radians(&self) -> Self;	
 }
 
 impl Angle for f64{
 	fn degrees(&self) -> f64 {
-		self / PI * 180.0
+		self / ::std::f64::consts::PI * 180.0
 	}
 
 	fn radians(&self) -> f64 {
-		self * PI / 180.0
+		self * ::std::f64::consts::PI / 180.0
 	}
 } 
 
-impl fmt::Display for Vec3 {
+impl Angle for f32{
+	fn degrees(&self) -> f32 {
+		self / ::std::f32::consts::PI * 180.0
+	}
+
+	fn radians(&self) -> f32 {
+		self * ::std::f32::consts::PI / 180.0
+	}
+}
+
+impl<T: Float> fmt::Display for Vector3<T> {
 	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
 		write!(f, "({}, {}, {})", self.0, self.1, self.2)
 	}
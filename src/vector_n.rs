@@ -0,0 +1,183 @@
+use std::ops::{Add, AddAssign, Div, Index, IndexMut, Mul, Neg, Sub, SubAssign};
+use std::convert::From;
+
+use super::scalar::Float;
+use super::vector::{Vector3, Vector4};
+
+// vector of any fixed dimension. Vector3 and Vector4 keep their own types and
+// convert to and from VectorN<T, 3> / VectorN<T, 4> without loss.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct VectorN<T, const N: usize>(pub [T; N]);
+
+pub type VecN<const N: usize> = VectorN<f64, N>;
+pub type VecNf<const N: usize> = VectorN<f32, N>;
+pub type Vec2 = VecN<2>;
+pub type Vec2f = VecNf<2>;
+// spatial vectors such as twists, (linear, angular)
+pub type Vec6 = VecN<6>;
+pub type Vec6f = VecNf<6>;
+
+impl<T: Float, const N: usize> Add for VectorN<T, N> {
+	type Output = VectorN<T, N>;
+
+	fn add(self, other: VectorN<T, N>) -> VectorN<T, N> {
+		self.zip_map(other, |a, b| a + b)
+	}
+}
+
+impl<T: Float, const N: usize> Sub for VectorN<T, N> {
+	type Output = VectorN<T, N>;
+
+	fn sub(self, other: VectorN<T, N>) -> VectorN<T, N> {
+		self.zip_map(other, |a, b| a - b)
+	}
+}
+
+impl<T: Float, const N: usize> Neg for VectorN<T, N> {
+	type Output = VectorN<T, N>;
+
+	fn neg(self) -> VectorN<T, N> {
+		self.map(|a| -a)
+	}
+}
+
+impl<T: Float, const N: usize> Mul<T> for VectorN<T, N> {
+	type Output = VectorN<T, N>;
+
+	fn mul(self, s: T) -> VectorN<T, N> {
+		self.map(|a| a * s)
+	}
+}
+
+impl<T: Float, const N: usize> Div<T> for VectorN<T, N> {
+	type Output = VectorN<T, N>;
+
+	fn div(self, s: T) -> VectorN<T, N> {
+		self.map(|a| a / s)
+	}
+}
+
+impl<T: Float, const N: usize> AddAssign for VectorN<T, N> {
+	fn add_assign(&mut self, other: VectorN<T, N>) {
+		*self = *self + other;
+	}
+}
+
+impl<T: Float, const N: usize> SubAssign for VectorN<T, N> {
+	fn sub_assign(&mut self, other: VectorN<T, N>) {
+		*self = *self - other;
+	}
+}
+
+impl<T: Float, const N: usize> Index<usize> for VectorN<T, N> {
+	type Output = T;
+
+	fn index(&self, i: usize) -> &T {
+		&self.0[i]
+	}
+}
+
+impl<T: Float, const N: usize> IndexMut<usize> for VectorN<T, N> {
+	fn index_mut(&mut self, i: usize) -> &mut T {
+		&mut self.0[i]
+	}
+}
+
+impl<const N: usize> From<VectorN<f32, N>> for VectorN<f64, N> {
+	fn from(o: VectorN<f32, N>) -> VectorN<f64, N> {
+		o.cast()
+	}
+}
+
+impl<T: Float> From<Vector3<T>> for VectorN<T, 3> {
+	fn from(v: Vector3<T>) -> VectorN<T, 3> {
+		VectorN([v.x(), v.y(), v.z()])
+	}
+}
+
+impl<T: Float> From<VectorN<T, 3>> for Vector3<T> {
+	fn from(v: VectorN<T, 3>) -> Vector3<T> {
+		Vector3::new(v.0[0], v.0[1], v.0[2])
+	}
+}
+
+impl<T: Float> From<Vector4<T>> for VectorN<T, 4> {
+	fn from(v: Vector4<T>) -> VectorN<T, 4> {
+		VectorN([v.x(), v.y(), v.z(), v.w()])
+	}
+}
+
+impl<T: Float> From<VectorN<T, 4>> for Vector4<T> {
+	fn from(v: VectorN<T, 4>) -> Vector4<T> {
+		Vector4::new(v.0[0], v.0[1], v.0[2], v.0[3])
+	}
+}
+
+impl<T: Float, const N: usize> VectorN<T, N> {
+	pub fn new(v: [T; N]) -> VectorN<T, N> {
+		VectorN(v)
+	}
+
+	pub fn zero() -> VectorN<T, N> {
+		VectorN([T::zero(); N])
+	}
+
+	// e_i, panics when i >= N
+	pub fn unit(i: usize) -> VectorN<T, N> {
+		let mut v = VectorN::zero();
+		v.0[i] = T::one();
+		v
+	}
+
+	pub fn cast<U: Float>(&self) -> VectorN<U, N> {
+		self.map_to(|a| U::from_f64(a.to_f64()))
+	}
+
+	pub fn map<F: Fn(T) -> T>(&self, f: F) -> VectorN<T, N> {
+		self.map_to(f)
+	}
+
+	fn map_to<U: Float, F: Fn(T) -> U>(&self, f: F) -> VectorN<U, N> {
+		let mut r = VectorN([U::zero(); N]);
+		for (e, a) in r.0.iter_mut().zip(self.0.iter()) {
+			*e = f(*a);
+		}
+		r
+	}
+
+	pub fn zip_map<F: Fn(T, T) -> T>(&self, other: VectorN<T, N>, f: F) -> VectorN<T, N> {
+		let mut r = *self;
+		for (e, b) in r.0.iter_mut().zip(other.0.iter()) {
+			*e = f(*e, *b);
+		}
+		r
+	}
+
+	pub fn as_array(&self) -> [T; N] {
+		self.0
+	}
+
+	pub fn dot(&self, other: &VectorN<T, N>) -> T {
+		self.0.iter().zip(other.0.iter()).fold(T::zero(), |s, (a, b)| s + *a * *b)
+	}
+
+	pub fn scalar_mul(&self, s: T) -> VectorN<T, N> {
+		*self * s
+	}
+
+	pub fn scalar_div(&self, s: T) -> VectorN<T, N> {
+		*self / s
+	}
+
+	pub fn length(&self) -> T {
+		self.dot(self).sqrt()
+	}
+
+	pub fn unitize(&self) -> VectorN<T, N> {
+		*self / self.length()
+	}
+
+	pub fn distance(&self, other: VectorN<T, N>) -> T {
+		(*self - other).length()
+	}
+}